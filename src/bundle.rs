@@ -0,0 +1,67 @@
+//! Packaging of the generated files into a zip for deployment
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Write `sources` into a zip archive at `bundle`.
+/// Each source is a file or directory along with its path within the archive, directories are added recursively.
+/// When multiple sources contain the same path the first one wins, matching the precedence of the dev server.
+pub fn write_zip(sources: &[(PathBuf, String)], bundle: &Path) -> Result<(), String> {
+    let file = File::create(bundle)
+        .map_err(|err| format!("Failed to create the bundle {bundle:?}: {err}"))?;
+    let mut zip = ZipWriter::new(file);
+    let mut added = HashSet::new();
+    sources
+        .iter()
+        .try_for_each(|(path, name)| add_path(&mut zip, &mut added, path, name, bundle))
+        .and_then(|_| zip.finish().map(|_| ()).map_err(|err| err.to_string()))
+        .map_err(|err| format!("Failed to write the bundle {bundle:?}: {err}"))
+}
+
+fn add_path(
+    zip: &mut ZipWriter<File>,
+    added: &mut HashSet<String>,
+    path: &Path,
+    name: &str,
+    bundle: &Path,
+) -> Result<(), String> {
+    // The bundle may have been placed inside a directory being bundled
+    if path == bundle {
+        return Ok(());
+    }
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    if path.is_dir() {
+        if !name.is_empty() && added.insert(format!("{name}/")) {
+            zip.add_directory(name, options)
+                .map_err(|err| err.to_string())?;
+        }
+        let mut entries = std::fs::read_dir(path)
+            .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| format!("Failed to read {path:?}: {err}"))?;
+        // Sort so that the archive is identical between builds of the same files
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let file_name = entry.file_name();
+            let file_name = file_name
+                .to_str()
+                .ok_or_else(|| format!("The file name {file_name:?} is not valid utf-8"))?;
+            let child = if name.is_empty() {
+                file_name.to_owned()
+            } else {
+                format!("{name}/{file_name}")
+            };
+            add_path(zip, added, &entry.path(), &child, bundle)?;
+        }
+    } else if added.insert(name.to_owned()) {
+        let contents =
+            std::fs::read(path).map_err(|err| format!("Failed to read {path:?}: {err}"))?;
+        zip.start_file(name, options)
+            .map_err(|err| err.to_string())?;
+        zip.write_all(&contents).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Minimal ANSI coloring of run-wasm's own messages, matching the style cargo uses for its messages
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The value of `--color`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        }
+    }
+}
+
+/// Color the messages printed for the rest of the process according to `choice`.
+/// When `choice` is None the `CARGO_TERM_COLOR` env var is used, like cargo does.
+pub fn init(choice: Option<ColorChoice>) {
+    let choice = choice
+        .or_else(|| {
+            std::env::var("CARGO_TERM_COLOR")
+                .ok()
+                .and_then(|x| ColorChoice::parse(&x))
+        })
+        .unwrap_or(ColorChoice::Auto);
+    let enabled = match choice {
+        ColorChoice::Auto => std::io::stdout().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if ENABLED.load(Ordering::Relaxed) {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_owned()
+    }
+}
+
+/// Bold red, for the `error:` label
+pub fn error(text: &str) -> String {
+    paint("1;31", text)
+}
+
+/// Bold yellow, for the `warning:` label
+pub fn warning(text: &str) -> String {
+    paint("1;33", text)
+}
+
+/// Bold cyan, for the `hint:` label
+pub fn hint(text: &str) -> String {
+    paint("1;36", text)
+}
+
+/// Bold green, for status messages
+pub fn status(text: &str) -> String {
+    paint("1;32", text)
+}
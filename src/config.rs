@@ -0,0 +1,39 @@
+//! Defaults for the CLI loaded from a `run-wasm.toml` file in the workspace root
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "run-wasm.toml";
+
+/// The settings that can be specified in `run-wasm.toml`.
+/// Each of these is only used when the matching CLI flag is not.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// A css file, relative to the workspace root, to include in the page
+    pub css: Option<PathBuf>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub spa: bool,
+    #[serde(default)]
+    pub access_log: bool,
+}
+
+impl Config {
+    /// Load the config file from `workspace_root`, if there is one
+    pub fn load(workspace_root: &Path) -> Result<Option<Config>, String> {
+        let path = workspace_root.join(FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read {path:?}: {err}"))?;
+        let mut config: Config =
+            toml::from_str(&contents).map_err(|err| format!("Failed to parse {path:?}: {err}"))?;
+        config.css = config.css.map(|css| workspace_root.join(css));
+        Ok(Some(config))
+    }
+}
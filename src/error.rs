@@ -0,0 +1,59 @@
+//! The error returned by the public methods of `RunWasm`
+use std::fmt;
+
+/// Why building or serving failed.
+///
+/// The `Display` output is the message the `cargo run-wasm` CLI prints, so most callers can just print it.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RunWasmError {
+    /// The settings are invalid or refer to packages, targets, profiles or files that do not exist
+    InvalidConfig { reason: String },
+    /// cargo failed to build the wasm, it will have already printed its own error
+    CargoBuildFailed,
+    /// cargo succeeded but did not produce a wasm file for the target
+    ArtifactNotFound { kind: &'static str, name: String },
+    /// Some targets failed to build with `with_keep_going`, the individual errors were already printed
+    TargetsFailed { failed: Vec<String>, total: usize },
+    /// wasm-bindgen failed to generate the js glue
+    BindgenFailed { reason: String },
+    /// The dev server could not listen on `addr`
+    ServerBindFailed { addr: String, reason: String },
+    /// The wasm run with node or deno exited unsuccessfully with `code`, the runtime will have already printed the reason
+    RuntimeExited { code: i32 },
+    /// Any other failure, e.g. an io error while writing the output
+    Other(String),
+}
+
+impl fmt::Display for RunWasmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunWasmError::InvalidConfig { reason } => write!(f, "{reason}"),
+            RunWasmError::CargoBuildFailed => write!(f, "Failed due to cargo error"),
+            RunWasmError::ArtifactNotFound { kind, name } => write!(
+                f,
+                "Cargo did not produce a wasm file for the {kind} `{name}`, maybe you used `--package NAME` on a package that has no binary?"
+            ),
+            RunWasmError::TargetsFailed { failed, total } => write!(
+                f,
+                "Failed to build {} of {total} targets: {}",
+                failed.len(),
+                failed.join(", ")
+            ),
+            RunWasmError::BindgenFailed { reason } => write!(f, "{reason}"),
+            RunWasmError::ServerBindFailed { addr, reason } => {
+                write!(f, "Could not bind the dev server to {addr}: {reason}")
+            }
+            RunWasmError::RuntimeExited { code } => write!(f, "The wasm exited with code {code}"),
+            RunWasmError::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for RunWasmError {}
+
+impl From<String> for RunWasmError {
+    fn from(message: String) -> Self {
+        RunWasmError::Other(message)
+    }
+}
@@ -1,11 +1,29 @@
 #![allow(clippy::new_without_default)]
 
+mod bundle;
+mod color;
+mod config;
+mod error;
+mod metadata;
+mod minify;
+mod server;
+mod source_map;
 mod target_dir;
+mod template;
+mod wasm;
 
+use color::ColorChoice;
+use config::Config;
+pub use error::RunWasmError;
+use metadata::CargoMetadata;
 use pico_args::Arguments;
 use std::env;
-use std::ffi::OsStr;
-use std::process::Command;
+use std::ffi::{OsStr, OsString};
+use std::io::{BufRead, BufReader, Read};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use target_dir::CargoDirectories;
 
 const HELP: &str = "\
@@ -14,18 +32,63 @@ cargo run-wasm
 Hosts a binary or example of the local package as wasm in a local web server.
 
 USAGE:
-  cargo run-wasm [OPTIONS]
+  cargo run-wasm [+toolchain] [OPTIONS]
 
 OPTIONS:
   cargo run-wasm custom options:
+    --all-examples, --examples   Build every example, each one is served from its own subdirectory
+    --path <DIR>                 Run the package in DIR instead of selecting it with --package, it may be outside of the workspace
+    --print-config               Print the settings run-wasm will use and exit
+    --bindgen-info               Print the wasm-bindgen and toolchain versions run-wasm will use and exit,
+                                 useful for debugging wasm-bindgen version mismatches
     --build-only                 Only build the WASM artifacts, do not run the dev server
+    --echo-html                  Print the generated index.html, useful for debugging the page
+    --fullscreen-canvas          Add a canvas with the id 'run-wasm-canvas' to the page that is kept resized to fill the window
+    --html-only                  Skip building the wasm and only regenerate index.html, when a previous build exists
+    --wasm-bindgen-path <PATH>   Generate the js glue with this wasm-bindgen CLI instead of the built in one,
+                                 for when the wasm-bindgen version of the project does not match run-wasm's
+    --no-demangle                Do not demangle the rust symbol names in the wasm output
+    --split-linked-modules       Output js snippets from `#[wasm_bindgen(module = ...)]` as separate files instead of inlining them
+    --encode-into <MODE>         When the js glue uses `TextEncoder.encodeInto` for passing strings to wasm: test, always or never
+    --inline-wasm                Embed the wasm and js into the generated index.html, producing a single self contained file
+    --runtime <RUNTIME>          Where to run the wasm: browser, node or deno (default 'browser').
+                                 node and deno execute the wasm directly instead of starting the dev server
+    --print-paths                Print the paths of the generated files as KEY=value lines that can be sourced by a shell
+    --bundle <OUT.zip>           Zip up the generated files into OUT.zip, ready for uploading to a static host
+    --output-dir <DIR>           Write the generated files into DIR instead of target/wasm-examples/NAME,
+                                 combine with --build-only when serving DIR with another server
+    --clean                      Delete the previously generated files of the target before generating them again
+    --shared-target-dir          Build into the regular target directory instead of a separate wasm specific one.
+                                 Avoids duplicating build artifacts but will trigger full rebuilds when the
+                                 rustflags used for native and wasm builds differ
     --host <HOST>                Makes the dev server listen on host (default 'localhost')
     --port <PORT>                Makes the dev server listen on port (default '8000')
+    --base-path <PATH>           Serve the page below PATH e.g. /app/, like an app deployed to a subdirectory of a domain
+    --ipv4                       Only consider the IPv4 addresses the host resolves to
+    --ipv6                       Only consider the IPv6 addresses the host resolves to
+    --no-cache                   Tell the browser not to cache any files (default)
+    --immutable-cache            Tell the browser to cache files with a content hash in their name indefinitely, emulating a production deployment
+    --wasm-rustflags <FLAGS>     RUSTFLAGS to use for the wasm build only e.g. '-C target-feature=+atomics'
+    --target-feature <FEATURES>  Comma separated wasm target features to enable for the wasm build e.g. 'simd128'
+    --serve-timeout <SECS>       Shut down the dev server after the given number of seconds
+    --proxy <PREFIX=URL>         Forward requests for PREFIX and the paths below it to the server at URL e.g. /api=http://localhost:3000
+    --static-dir <DIR>           Also serve the files in DIR, can be used multiple times with earlier directories taking precedence
+    --allowed-host <HOST>        Only respond to requests for HOST, can be used multiple times. Protects against DNS rebinding
+                                 when listening on a public address, localhost and ip addresses are always allowed
+    --access-log                 Print every request received by the dev server along with the response status
+    --log-level <LEVEL>          Set `window.RUN_WASM_LOG_LEVEL` on the page, for the app to configure its logging with
+    --live-reload                Reload the page when the generated files change, e.g. when rebuilt by `cargo watch -x run-wasm`
+    --spa                        Serve index.html for any path that does not match a file, for apps using client side routing
+    --open                       Open the page in the default browser once the dev server is listening.
+                                 Skipped when a CI environment is detected
+    --force-open                 Open the page in the default browser even when a CI environment is detected
 
   cargo run default options:
     -q, --quiet                     Do not print cargo log messages
         --bin [<NAME>]              Name of the bin target to run
         --example [<NAME>]          Name of the example target to run
+                                    The names of --bin and --example may contain * and ? wildcards to run every matching target,
+                                    quote the pattern to prevent the shell expanding it e.g. --example 'gui_*'
     -p, --package [<SPEC>...]       Package with the target to run
     -v, --verbose                   Use verbose output (-vv very verbose/build.rs output)
     -j, --jobs <N>                  Number of parallel jobs, defaults to # of CPUs
@@ -43,36 +106,137 @@ OPTIONS:
     -Z <FLAG>                       Unstable (nightly-only) flags to Cargo, see 'cargo -Z help' for
                                     details
         --manifest-path <PATH>      Path to Cargo.toml
-        --message-format <FMT>      Error format
         --unit-graph                Output build graph in JSON (unstable)
         --ignore-rust-version       Ignore `rust-version` specification in packages
         --timings[=<FMTS>...]       Timing output formats (unstable) (comma separated): html, json
     -h, --help                      Print help information
+    -V, --version                   Print the versions of run-wasm and the wasm-bindgen it generates bindings with
 
-At least one of `--package`, `--bin` or `--example` must be used.
+At least one of `--package`, `--path`, `--bin`, `--example` or `--all-examples` must be used.
+
+Defaults for some options can be set in a run-wasm.toml file in the workspace root e.g.
+  css = 'examples/style.css'
+  host = '0.0.0.0'
+  port = 8080
+  profile = 'release'
+  features = ['webgl']
+  spa = true
+  access-log = true
 
 Normally you can run just `cargo run` to run the main binary of the current package.
 The equivalent of that is `cargo run-wasm --package name_of_current_package`
 ";
 
-struct Args {
+/// The command line arguments accepted by run-wasm, see `cargo run-wasm --help` for the full list.
+///
+/// This allows a tool with its own argument parser to forward just the run-wasm arguments via [`RunWasmArgs::from_vec`]
+/// and then launch run-wasm with [`run_wasm_cli_with_args`].
+pub struct RunWasmArgs {
     help: bool,
+    version: bool,
+    print_config: bool,
+    bindgen_info: bool,
     profile: Option<String>,
     build_only: bool,
+    demangle: bool,
+    split_linked_modules: bool,
+    external_bindgen: Option<PathBuf>,
+    print_paths: bool,
+    fullscreen_canvas: bool,
+    encode_into: Option<EncodeInto>,
+    echo_html: bool,
+    html_only: bool,
+    inline_wasm: bool,
+    runtime: Runtime,
+    bundle: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    shared_target_dir: bool,
+    clean_output: bool,
+    wasm_rustflags: Option<String>,
+    target_features: Vec<String>,
     host: Option<String>,
     port: Option<String>,
+    base_path: Option<String>,
+    address_family: Option<AddressFamily>,
+    cache_policy: CachePolicy,
+    spa_fallback: bool,
+    log_level: Option<String>,
+    access_log: bool,
+    live_reload: bool,
+    open: bool,
+    force_open: bool,
+    serve_timeout: Option<Duration>,
+    proxies: Vec<(String, String)>,
+    static_dirs: Vec<PathBuf>,
+    allowed_hosts: Vec<String>,
+    /// Dev server options given alongside --build-only, warned about once the output colors are known
+    ignored_options: Vec<&'static str>,
+    locked: bool,
+    offline: bool,
+    frozen: bool,
+    keep_going: bool,
+    features: Vec<String>,
     build_args: Vec<String>,
-    package: Option<String>,
+    toolchain: Option<String>,
+    color: Option<ColorChoice>,
+    packages: Vec<String>,
+    package_path: Option<PathBuf>,
     example: Option<String>,
     bin: Option<String>,
+    sole_example: bool,
+    sole_bin: bool,
+    all_examples: bool,
 }
 
-impl Args {
-    fn from_env() -> Result<Self, String> {
-        let mut args = Arguments::from_env();
+impl RunWasmArgs {
+    /// Parse the arguments the process was launched with
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_vec(env::args_os().skip(1).collect())
+    }
+
+    /// Parse the given arguments, which should not include the executable name
+    pub fn from_vec(mut raw_args: Vec<OsString>) -> Result<Self, String> {
+        // Checked on the raw args as pico-args does not understand the `--option=value` spelling.
+        // run-wasm sets the message format itself to find the wasm file cargo produced.
+        let banned_options = ["--target", "--target-dir", "--message-format"];
+        let used_banned_options: Vec<&str> = banned_options
+            .iter()
+            .copied()
+            .filter(|option| {
+                raw_args.iter().any(|arg| {
+                    let arg = arg.to_string_lossy();
+                    arg == *option
+                        || arg
+                            .strip_prefix(option)
+                            .is_some_and(|rest| rest.starts_with('='))
+                })
+            })
+            .collect();
+        if !used_banned_options.is_empty() {
+            return Err(format!(
+                "cargo-run-wasm does not support the {} option",
+                used_banned_options.join(" or ")
+            ));
+        }
+
+        // Like cargo, a toolchain can be selected with a leading `+toolchain` argument
+        let toolchain = match raw_args.first().and_then(|x| x.to_str()) {
+            Some(arg) if arg.starts_with('+') => {
+                let toolchain = arg[1..].to_owned();
+                raw_args.remove(0);
+                Some(toolchain)
+            }
+            _ => None,
+        };
+
+        let sole_example = remove_flag_without_value(&mut raw_args, "--example");
+        let sole_bin = remove_flag_without_value(&mut raw_args, "--bin");
+        let mut args = Arguments::from_vec(raw_args);
 
         let release_arg = args.contains("--release") || args.contains("-r");
-        let profile_arg: Option<String> = args.opt_value_from_str("--profile").unwrap();
+        let profile_arg: Option<String> = args
+            .opt_value_from_str("--profile")
+            .map_err(|err| format!("Invalid --profile: {err}"))?;
         if release_arg && profile_arg.is_some() {
             return Err(r#"conflicting usage of --profile and --release.
 The `--release` flag is the same as `--profile=release`.
@@ -88,49 +252,382 @@ Remove one flag or the other to continue."#
         });
 
         let build_only = args.contains("--build-only");
+        let inline_wasm = args.contains("--inline-wasm");
+        let html_only = args.contains("--html-only");
+        let echo_html = args.contains("--echo-html");
+        let fullscreen_canvas = args.contains("--fullscreen-canvas");
+        let print_paths = args.contains("--print-paths");
+        let demangle = !args.contains("--no-demangle");
+        let split_linked_modules = args.contains("--split-linked-modules");
+        let external_bindgen: Option<PathBuf> = args
+            .opt_value_from_os_str("--wasm-bindgen-path", |x| {
+                Ok::<_, std::convert::Infallible>(PathBuf::from(x))
+            })
+            .map_err(|err| format!("Invalid --wasm-bindgen-path: {err}"))?;
+        let encode_into = match args
+            .opt_value_from_str::<_, String>("--encode-into")
+            .map_err(|err| format!("Invalid --encode-into: {err}"))?
+        {
+            None => None,
+            Some(mode) => Some(match mode.as_str() {
+                "test" => EncodeInto::Test,
+                "always" => EncodeInto::Always,
+                "never" => EncodeInto::Never,
+                _ => {
+                    return Err(format!(
+                        "Invalid --encode-into `{mode}`, expected one of test, always or never"
+                    ))
+                }
+            }),
+        };
+        let runtime = match args
+            .opt_value_from_str::<_, String>("--runtime")
+            .map_err(|err| format!("Invalid --runtime: {err}"))?
+        {
+            None => Runtime::Browser,
+            Some(runtime) => match runtime.as_str() {
+                "browser" => Runtime::Browser,
+                "node" => Runtime::Node,
+                "deno" => Runtime::Deno,
+                _ => {
+                    return Err(format!(
+                        "Invalid --runtime `{runtime}`, expected one of browser, node or deno"
+                    ))
+                }
+            },
+        };
+        let bundle: Option<PathBuf> = args
+            .opt_value_from_os_str("--bundle", |x| {
+                Ok::<_, std::convert::Infallible>(PathBuf::from(x))
+            })
+            .map_err(|err| format!("Invalid --bundle: {err}"))?;
+        let output_dir: Option<PathBuf> = args
+            .opt_value_from_os_str("--output-dir", |x| {
+                Ok::<_, std::convert::Infallible>(PathBuf::from(x))
+            })
+            .map_err(|err| format!("Invalid --output-dir: {err}"))?;
+        let shared_target_dir = args.contains("--shared-target-dir");
+        let clean_output = args.contains("--clean");
+        let wasm_rustflags: Option<String> = args
+            .opt_value_from_str("--wasm-rustflags")
+            .map_err(|err| format!("Invalid --wasm-rustflags: {err}"))?;
+        let target_features = args
+            .values_from_str::<_, String>("--target-feature")
+            .map_err(|err| format!("Invalid --target-feature: {err}"))?
+            .iter()
+            .flat_map(|x| x.split(','))
+            .map(|x| x.trim().to_owned())
+            .filter(|x| !x.is_empty())
+            .collect();
         let help = args.contains("--help") || args.contains("-h");
+        let version = args.contains("--version") || args.contains("-V");
+        let print_config = args.contains("--print-config");
+        let bindgen_info = args.contains("--bindgen-info");
 
-        let host: Option<String> = args.opt_value_from_str("--host").unwrap();
-        let port: Option<String> = args.opt_value_from_str("--port").unwrap();
-
-        let package: Option<String> = args
-            .opt_value_from_str("--package")
-            .unwrap()
-            .or_else(|| args.opt_value_from_str("-p").unwrap());
-        let example: Option<String> = args.opt_value_from_str("--example").unwrap();
-        let bin: Option<String> = args.opt_value_from_str("--bin").unwrap();
-
-        let banned_options = ["--target", "--target-dir"];
-        for option in banned_options {
-            if args
-                .opt_value_from_str::<_, String>(option)
-                .unwrap()
-                .is_some()
-            {
-                return Err(format!(
-                    "cargo-run-wasm does not support the {option} option"
-                ));
-            }
+        let host: Option<String> = args
+            .opt_value_from_str("--host")
+            .map_err(|err| format!("Invalid --host: {err}"))?;
+        let port: Option<String> = args
+            .opt_value_from_str("--port")
+            .map_err(|err| format!("Invalid --port: {err}"))?;
+        let base_path: Option<String> = args
+            .opt_value_from_str("--base-path")
+            .map_err(|err| format!("Invalid --base-path: {err}"))?;
+        let ipv4 = args.contains("--ipv4");
+        let ipv6 = args.contains("--ipv6");
+        if ipv4 && ipv6 {
+            return Err(r#"conflicting usage of --ipv4 and --ipv6.
+Remove one flag or the other to continue."#
+                .to_owned());
+        }
+        let address_family = if ipv4 {
+            Some(AddressFamily::Ipv4)
+        } else if ipv6 {
+            Some(AddressFamily::Ipv6)
+        } else {
+            None
+        };
+
+        let no_cache = args.contains("--no-cache");
+        let immutable_cache = args.contains("--immutable-cache");
+        if no_cache && immutable_cache {
+            return Err(r#"conflicting usage of --no-cache and --immutable-cache.
+Remove one flag or the other to continue."#
+                .to_owned());
+        }
+        let cache_policy = if immutable_cache {
+            CachePolicy::Immutable
+        } else {
+            CachePolicy::NoCache
+        };
+        let spa_fallback = args.contains("--spa");
+        let access_log = args.contains("--access-log");
+        let live_reload = args.contains("--live-reload");
+        let open = args.contains("--open");
+        let force_open = args.contains("--force-open");
+        let log_level: Option<String> = args
+            .opt_value_from_str("--log-level")
+            .map_err(|err| format!("Invalid --log-level: {err}"))?;
+        let serve_timeout = args
+            .opt_value_from_str::<_, u64>("--serve-timeout")
+            .map_err(|err| format!("Invalid --serve-timeout: {err}"))?
+            .map(Duration::from_secs);
+        let proxies = args
+            .values_from_str::<_, String>("--proxy")
+            .map_err(|err| format!("Invalid --proxy: {err}"))?
+            .into_iter()
+            .map(|proxy| match proxy.split_once('=') {
+                Some((prefix, target)) => proxy_address(target)
+                    .map(|_| (prefix.to_owned(), target.to_owned()))
+                    .map_err(|err| format!("Invalid --proxy `{proxy}`: {err}")),
+                None => Err(format!(
+                    "Invalid --proxy `{proxy}`, expected the form PREFIX=URL e.g. /api=http://localhost:3000"
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let static_dirs: Vec<PathBuf> = args
+            .values_from_os_str("--static-dir", |x| {
+                Ok::<_, std::convert::Infallible>(PathBuf::from(x))
+            })
+            .map_err(|err| format!("Invalid --static-dir: {err}"))?;
+        let allowed_hosts: Vec<String> = args
+            .values_from_str("--allowed-host")
+            .map_err(|err| format!("Invalid --allowed-host: {err}"))?;
+
+        let mut ignored_options = vec![];
+        if build_only {
+            let options = [
+                ("--host", host.is_some()),
+                ("--port", port.is_some()),
+                ("--base-path", base_path.is_some()),
+                ("--ipv4", ipv4),
+                ("--ipv6", ipv6),
+                ("--no-cache", no_cache),
+                ("--immutable-cache", immutable_cache),
+                ("--spa", spa_fallback),
+                ("--access-log", access_log),
+                ("--open", open),
+                ("--force-open", force_open),
+                ("--serve-timeout", serve_timeout.is_some()),
+                ("--proxy", !proxies.is_empty()),
+                ("--static-dir", !static_dirs.is_empty()),
+                ("--allowed-host", !allowed_hosts.is_empty()),
+            ];
+            ignored_options.extend(
+                options
+                    .iter()
+                    .filter(|(_, used)| *used)
+                    .map(|(option, _)| *option),
+            );
         }
 
-        let build_args = args
+        let locked = args.contains("--locked");
+        let offline = args.contains("--offline");
+        let frozen = args.contains("--frozen");
+        let keep_going = args.contains("--keep-going");
+        let mut features: Vec<String> = args
+            .values_from_str("--features")
+            .map_err(|err| format!("Invalid --features: {err}"))?;
+        features.extend(
+            args.values_from_str::<_, String>("-F")
+                .map_err(|err| format!("Invalid -F: {err}"))?,
+        );
+        let features = features.iter().flat_map(|x| split_features(x)).collect();
+
+        let mut packages: Vec<String> = args
+            .values_from_str("--package")
+            .map_err(|err| format!("Invalid --package: {err}"))?;
+        packages.extend(
+            args.values_from_str::<_, String>("-p")
+                .map_err(|err| format!("Invalid -p: {err}"))?,
+        );
+        let package_path: Option<PathBuf> = args
+            .opt_value_from_os_str("--path", |x| {
+                Ok::<_, std::convert::Infallible>(PathBuf::from(x))
+            })
+            .map_err(|err| format!("Invalid --path: {err}"))?;
+        let example: Option<String> = args
+            .opt_value_from_str("--example")
+            .map_err(|err| format!("Invalid --example: {err}"))?;
+        let bin: Option<String> = args
+            .opt_value_from_str("--bin")
+            .map_err(|err| format!("Invalid --bin: {err}"))?;
+        let all_examples = args.contains("--all-examples") || args.contains("--examples");
+
+        let color = match args
+            .opt_value_from_str::<_, String>("--color")
+            .map_err(|err| format!("Invalid --color: {err}"))?
+        {
+            None => None,
+            Some(color) => Some(ColorChoice::parse(&color).ok_or_else(|| {
+                format!("Invalid --color `{color}`, expected one of auto, always or never")
+            })?),
+        };
+
+        let mut build_args: Vec<String> = args
             .finish()
             .into_iter()
-            .map(|x| x.into_string().unwrap())
-            .collect();
+            .map(|x| {
+                x.into_string()
+                    .map_err(|x| format!("The argument {x:?} is not valid utf-8"))
+            })
+            .collect::<Result<_, _>>()?;
+        // run-wasm colors its own messages too, but cargo still needs to be told
+        if let Some(color) = color {
+            build_args.extend(["--color".to_owned(), color.as_str().to_owned()]);
+        }
 
-        Ok(Args {
+        Ok(RunWasmArgs {
             help,
+            version,
+            print_config,
+            bindgen_info,
             profile,
             build_only,
+            demangle,
+            split_linked_modules,
+            external_bindgen,
+            print_paths,
+            fullscreen_canvas,
+            encode_into,
+            echo_html,
+            html_only,
+            inline_wasm,
+            runtime,
+            bundle,
+            output_dir,
+            shared_target_dir,
+            clean_output,
+            wasm_rustflags,
+            target_features,
             host,
             port,
+            base_path,
+            address_family,
+            cache_policy,
+            spa_fallback,
+            log_level,
+            access_log,
+            live_reload,
+            open,
+            force_open,
+            serve_timeout,
+            proxies,
+            static_dirs,
+            allowed_hosts,
+            ignored_options,
+            locked,
+            offline,
+            frozen,
+            keep_going,
+            features,
             build_args,
-            package,
+            toolchain,
+            color,
+            packages,
+            package_path,
             example,
             bin,
+            sole_example,
+            sole_bin,
+            all_examples,
         })
     }
+
+    /// Use the settings from a `run-wasm.toml` for anything that was not specified on the command line
+    fn apply_config(&mut self, config: Config, css: &mut String) -> Result<(), String> {
+        if let Some(path) = config.css {
+            let file_css = std::fs::read_to_string(&path)
+                .map_err(|err| format!("Failed to read the css file {path:?}: {err}"))?;
+            css.push('\n');
+            css.push_str(&file_css);
+        }
+        self.host = self.host.take().or(config.host);
+        self.port = self
+            .port
+            .take()
+            .or(config.port.map(|port| port.to_string()));
+        self.profile = self.profile.take().or(config.profile);
+        let all_features = self.build_args.iter().any(|x| x == "--all-features");
+        if self.features.is_empty() && !all_features {
+            self.features = config.features;
+        }
+        self.spa_fallback |= config.spa;
+        self.access_log |= config.access_log;
+        Ok(())
+    }
+}
+
+/// Catch a broken pipeline before starting the dev server, rather than serving a directory that cant load the app
+fn check_index(index: &Path) -> Result<(), String> {
+    if index.is_file() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{index:?} does not exist so the dev server would have nothing to serve, was it removed by the post build hook?"
+        ))
+    }
+}
+
+/// The name of the wasm file cargo produces for the target `name`.
+/// cargo replaces hyphens in target names with underscores when naming artifacts.
+fn wasm_file_name(name: &str) -> String {
+    format!("{}.wasm", name.replace('-', "_"))
+}
+
+/// Returns true if `name` contains wildcards, selecting multiple targets
+fn is_pattern(name: &str) -> bool {
+    name.contains(&['*', '?'][..])
+}
+
+/// Match `name` against a pattern where `*` matches any sequence of characters and `?` matches any single character
+fn pattern_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` and the position in name it was matched up to, to backtrack to
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Split a `--features` value into each feature, following cargo in allowing both space and comma separators.
+/// Each feature is otherwise kept as is, so `pkg/feature` and `dep:name` are preserved.
+fn split_features(features: &str) -> impl Iterator<Item = String> + '_ {
+    features
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_owned())
+}
+
+/// If `flag` is present in `args` without a value following it, remove it and return true.
+fn remove_flag_without_value(args: &mut Vec<OsString>, flag: &str) -> bool {
+    if let Some(i) = args.iter().position(|arg| *arg == *flag) {
+        let has_value = args
+            .get(i + 1)
+            .map(|next| !next.to_string_lossy().starts_with('-'))
+            .unwrap_or(false);
+        if !has_value {
+            args.remove(i);
+            return true;
+        }
+    }
+    false
 }
 
 /// Low level control over run-wasm for integration within your own custom xtask
@@ -144,28 +641,172 @@ Remove one flag or the other to continue."#
 /// It will block forever to keep the webserver running until killed with ctrl-c or similar
 pub struct RunWasm {
     css: String,
+    css_strict: bool,
+    index_file: String,
+    base_path: String,
+    init_script: String,
+    cargo: Option<PathBuf>,
+    toolchain: Option<String>,
+    prebuilt_wasm: Option<PathBuf>,
+    viewport_meta: bool,
+    preload_wasm: bool,
+    meta: Vec<(String, String)>,
+    head: String,
+    lang: String,
+    dir: TextDirection,
+    import_map: Option<serde_json::Value>,
     profile: Option<String>,
     bin: Option<String>,
     example: Option<String>,
-    package: Option<String>,
+    sole_example: bool,
+    sole_bin: bool,
+    all_examples: bool,
+    packages: Vec<String>,
+    package_path: Option<PackagePath>,
+    package_path_error: Option<String>,
+    locked: bool,
+    offline: bool,
+    frozen: bool,
+    keep_going: bool,
+    jobs: Option<usize>,
+    features: Vec<String>,
+    build_std: Vec<String>,
     cargo_build_args: Vec<String>,
     build_only: bool,
+    size_warning_threshold: Option<u64>,
+    validate_imports: bool,
+    demangle: bool,
+    split_linked_modules: bool,
+    omit_default_module_path: bool,
+    external_bindgen: Option<PathBuf>,
+    snip: Vec<String>,
+    print_paths: bool,
+    fullscreen_canvas: bool,
+    encode_into: Option<EncodeInto>,
+    echo_html: bool,
+    html_only: bool,
+    inline_wasm: bool,
+    minify_js: bool,
+    source_maps: bool,
+    runtime: Runtime,
+    bundle: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    examples_base_dir: Option<PathBuf>,
+    shared_target_dir: bool,
+    clean_output: bool,
+    wasm_rustflags: Option<String>,
+    target_features: Vec<String>,
     host: Option<String>,
     port: Option<String>,
+    address_family: Option<AddressFamily>,
+    unix_socket: Option<PathBuf>,
+    listener: Option<TcpListener>,
+    cache_policy: CachePolicy,
+    spa_fallback: bool,
+    log_level: Option<String>,
+    access_log: bool,
+    live_reload: bool,
+    open: bool,
+    force_open: bool,
+    quiet: bool,
+    verbose: u8,
+    serve_timeout: Option<Duration>,
+    proxies: Vec<(String, String)>,
+    proxy_errors: Vec<String>,
+    routes: Vec<(String, PathBuf)>,
+    files: Vec<(String, Vec<u8>)>,
+    static_dirs: Vec<PathBuf>,
+    allowed_hosts: Vec<String>,
+    mime_types: Vec<(String, String)>,
+    on_ready_command: Vec<String>,
+    post_build: Option<PostBuild>,
 }
 
+type PostBuild = Box<dyn FnOnce(&Path) -> Result<(), String> + Send + Sync>;
+
 impl RunWasm {
     pub fn new() -> Self {
         RunWasm {
             css: "".to_owned(),
+            css_strict: false,
+            index_file: "index.html".to_owned(),
+            base_path: "/".to_owned(),
+            init_script: "".to_owned(),
+            cargo: None,
+            toolchain: None,
+            prebuilt_wasm: None,
+            viewport_meta: true,
+            preload_wasm: true,
+            meta: vec![],
+            head: String::new(),
+            lang: "en".to_owned(),
+            dir: TextDirection::Ltr,
+            import_map: None,
             profile: None,
             bin: None,
             example: None,
-            package: None,
+            sole_example: false,
+            sole_bin: false,
+            all_examples: false,
+            packages: vec![],
+            package_path: None,
+            package_path_error: None,
+            locked: false,
+            offline: false,
+            frozen: false,
+            keep_going: false,
+            jobs: None,
+            features: vec![],
+            build_std: vec![],
             cargo_build_args: vec![],
             build_only: false,
+            size_warning_threshold: Some(10_000_000),
+            validate_imports: false,
+            demangle: true,
+            split_linked_modules: false,
+            omit_default_module_path: false,
+            external_bindgen: None,
+            snip: vec![],
+            print_paths: false,
+            fullscreen_canvas: false,
+            encode_into: None,
+            echo_html: false,
+            html_only: false,
+            inline_wasm: false,
+            minify_js: false,
+            source_maps: false,
+            runtime: Runtime::Browser,
+            bundle: None,
+            output_dir: None,
+            examples_base_dir: None,
+            shared_target_dir: false,
+            clean_output: false,
+            wasm_rustflags: None,
+            target_features: vec![],
             host: None,
             port: None,
+            address_family: None,
+            unix_socket: None,
+            listener: None,
+            cache_policy: CachePolicy::NoCache,
+            spa_fallback: false,
+            log_level: None,
+            access_log: false,
+            live_reload: false,
+            open: false,
+            force_open: false,
+            quiet: false,
+            verbose: 0,
+            serve_timeout: None,
+            proxies: vec![],
+            proxy_errors: vec![],
+            routes: vec![],
+            files: vec![],
+            static_dirs: vec![],
+            allowed_hosts: vec![],
+            mime_types: vec![],
+            on_ready_command: vec![],
+            post_build: None,
         }
     }
 
@@ -183,6 +824,7 @@ impl RunWasm {
         //
         // Someone could easily get around this with some extra spaces
         // but im not about to import regex or do a complicated implementation by hand.
+        // Users that need a more thorough check can enable `with_css_strict`.
         if css.contains("</style>") {
             panic!(
             "`</style>` detected in the css. This is disallowed to prevent injecting elements into the DOM."
@@ -192,9 +834,194 @@ impl RunWasm {
         self
     }
 
+    /// Apply a stricter check against the css escaping its `<style>` element.
+    /// Whitespace and case are ignored so that variations like `< / STYLE>` are caught and `<script` is rejected entirely.
+    /// The check is performed when building, failing the build if the css is rejected.
+    ///
+    /// This is only needed when serving css from an untrusted source.
+    pub fn with_css_strict(mut self, css_strict: bool) -> Self {
+        self.css_strict = css_strict;
+        self
+    }
+
+    /// The file name of the generated page, which the dev server serves when a directory is requested (default `index.html`)
+    pub fn with_index_file(mut self, index_file: &str) -> Self {
+        self.index_file = index_file.to_owned();
+        self
+    }
+
+    /// Serve the page below `base_path` instead of at the root of the dev server, e.g. `/app/`.
+    /// Useful for checking that an app deployed to a subdirectory of a domain does not request files from the root.
+    /// The leading and trailing `/` are optional.
+    pub fn with_base_path(mut self, base_path: &str) -> Self {
+        let base_path = base_path.trim_matches('/');
+        self.base_path = if base_path.is_empty() {
+            "/".to_owned()
+        } else {
+            format!("/{base_path}/")
+        };
+        self
+    }
+
+    /// javascript to run once the wasm module has been initialized.
+    /// The exports of the wasm module are accessible via the `wasm` variable.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// RunWasm::new()
+    ///     .with_init_script("wasm.start_app(window.location.hash);")
+    ///     .run();
+    /// ```
+    pub fn with_init_script(mut self, init_script: &str) -> Self {
+        if init_script.to_ascii_lowercase().contains("</script") {
+            panic!(
+                "`</script` detected in the init script. This is disallowed to prevent injecting elements into the DOM."
+            )
+        }
+        self.init_script = init_script.to_owned();
+        self
+    }
+
+    /// Set `window.RUN_WASM_LOG_LEVEL` to `log_level` before the wasm module is loaded.
+    /// The app can read this to configure its logging, e.g. `console_log` or `tracing-wasm`, without recompiling to change the verbosity.
+    pub fn with_log_level(mut self, log_level: &str) -> Self {
+        self.log_level = Some(log_level.to_owned());
+        self
+    }
+
+    /// The cargo executable used to build the project.
+    /// Defaults to the `CARGO` environment variable if set, otherwise `cargo` from the `PATH`.
+    pub fn with_cargo(mut self, cargo: PathBuf) -> Self {
+        self.cargo = Some(cargo);
+        self
+    }
+
+    /// Build with the given rustup toolchain, the equivalent of `cargo +toolchain build`.
+    /// This allows e.g. building with nightly for `with_build_std` while run-wasm itself is built with stable.
+    ///
+    /// The toolchain is selected via the `RUSTUP_TOOLCHAIN` env var, so `cargo` from the `PATH` is used unless `with_cargo` is also set.
+    pub fn with_toolchain(mut self, toolchain: &str) -> Self {
+        self.toolchain = Some(toolchain.to_owned());
+        self
+    }
+
+    /// Skip the cargo build and instead run wasm-bindgen on an already built wasm file.
+    /// When set, the package, bin and example selection is ignored and the file name is used as the name of the page.
+    pub fn with_prebuilt_wasm(mut self, prebuilt_wasm: PathBuf) -> Self {
+        self.prebuilt_wasm = Some(prebuilt_wasm);
+        self
+    }
+
+    /// Include a `<meta name="viewport">` tag that makes the page render at the device width on mobile devices.
+    /// Enabled by default.
+    pub fn with_viewport_meta(mut self, viewport_meta: bool) -> Self {
+        self.viewport_meta = viewport_meta;
+        self
+    }
+
+    /// Include a `<link rel="preload">` for the wasm file so the browser starts downloading it while the js glue is still loading.
+    /// Enabled by default. Has no effect with `with_inline_wasm`.
+    ///
+    /// Disable this if the app delays initializing the wasm, as the browser warns about preloaded resources that are not used soon after the page loads.
+    pub fn with_preload_wasm(mut self, preload_wasm: bool) -> Self {
+        self.preload_wasm = preload_wasm;
+        self
+    }
+
+    /// Add a `<meta name="{name}" content="{content}">` tag to the served webpage.
+    /// Can be called multiple times to add multiple tags.
+    pub fn with_meta(mut self, name: &str, content: &str) -> Self {
+        self.meta.push((name.to_owned(), content.to_owned()));
+        self
+    }
+
+    /// Add raw markup to the `<head>` of the served webpage, e.g. `<link rel="preconnect">` hints or web fonts.
+    /// It is placed after the css so stylesheets linked here take precedence.
+    /// Can be called multiple times, the markup is included in the order it was added.
+    pub fn with_head(mut self, head: &str) -> Self {
+        let lowercase = head.to_ascii_lowercase();
+        if lowercase.contains("</head") {
+            panic!(
+                "`</head` detected in the head markup. This is disallowed to prevent injecting elements into the body."
+            )
+        }
+        if lowercase.matches("</script").count() > lowercase.matches("<script").count() {
+            panic!(
+                "Unmatched `</script` detected in the head markup. This is disallowed to prevent injecting elements into the DOM."
+            )
+        }
+        self.head.push_str(head);
+        self.head.push('\n');
+        self
+    }
+
+    /// The language of the served webpage, set as the `lang` attribute of `<html>` (default `en`)
+    pub fn with_lang(mut self, lang: &str) -> Self {
+        self.lang = lang.to_owned();
+        self
+    }
+
+    /// The text direction of the served webpage, set as the `dir` attribute of `<html>` (default `ltr`).
+    /// Useful for testing how a UI handles right to left languages.
+    pub fn with_dir(mut self, dir: TextDirection) -> Self {
+        self.dir = dir;
+        self
+    }
+
+    /// Add a `<script type="importmap">` with the given json to the served webpage, for apps that import other js modules by name.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// RunWasm::new()
+    ///     .with_import_map(r#"{ "imports": { "plugin": "./plugins/plugin.js" } }"#)
+    ///     .run();
+    /// ```
+    pub fn with_import_map(mut self, import_map: &str) -> Self {
+        match serde_json::from_str(import_map) {
+            Ok(serde_json::Value::Object(import_map)) => {
+                self.import_map = Some(serde_json::Value::Object(import_map))
+            }
+            Ok(_) => panic!("The import map must be a json object"),
+            Err(err) => panic!("The import map is not valid json: {}", err),
+        }
+        self
+    }
+
+    /// Add a `<canvas id="run-wasm-canvas">` to the page that fills the whole window and is resized along with it.
+    /// The page margins are removed and any css from `with_css` is applied afterwards.
+    ///
+    /// The app is expected to find the canvas by its id and render into it, which is common for wgpu and winit apps.
+    pub fn with_fullscreen_canvas(mut self, fullscreen_canvas: bool) -> Self {
+        self.fullscreen_canvas = fullscreen_canvas;
+        self
+    }
+
     /// Package with the target to run
     pub fn with_package(mut self, package: Option<String>) -> Self {
-        self.package = package;
+        self.packages = package.into_iter().collect();
+        self
+    }
+
+    /// Build and serve the default binary of each of these packages.
+    /// When there are multiple packages, each one is served from its own subdirectory with a page at the root linking to each of them.
+    pub fn with_packages(mut self, packages: Vec<String>) -> Self {
+        self.packages = packages;
+        self
+    }
+
+    /// Run the package whose Cargo.toml is in the directory `path`, for when it is easier to refer to a package by its location than by its name.
+    /// The package is built with `--manifest-path`, so it does not need to be part of the current workspace.
+    /// This replaces selecting the package by name, so it can not be combined with `with_package` or `with_packages`.
+    pub fn with_package_path(mut self, path: PathBuf) -> Self {
+        // Any problem is reported when building, so the builder does not need to return a Result
+        match PackagePath::new(&path) {
+            Ok(package_path) => {
+                self.package_path = Some(package_path);
+                self.package_path_error = None;
+            }
+            Err(err) => {
+                self.package_path = None;
+                self.package_path_error = Some(err);
+            }
+        }
         self
     }
 
@@ -210,12 +1037,88 @@ impl RunWasm {
         self
     }
 
+    /// Run the only example in the package, this is what `--example` does when used without a name.
+    /// If `--package` is not used then all examples in the workspace are considered.
+    /// Fails if there is not exactly one example.
+    pub fn with_sole_example(mut self, sole_example: bool) -> Self {
+        self.sole_example = sole_example;
+        self
+    }
+
+    /// Run the only bin in the package, this is what `--bin` does when used without a name.
+    /// If `--package` is not used then all bins in the workspace are considered.
+    /// Fails if there is not exactly one bin.
+    pub fn with_sole_bin(mut self, sole_bin: bool) -> Self {
+        self.sole_bin = sole_bin;
+        self
+    }
+
+    /// Build every example in the package, or the whole workspace if no package is selected.
+    /// Each example is served from its own subdirectory with a page at the root linking to each of them.
+    ///
+    /// Every example is built separately, if `with_keep_going` is enabled then a failure to build one example will not prevent the others from being built.
+    pub fn with_all_examples(mut self, all_examples: bool) -> Self {
+        self.all_examples = all_examples;
+        self
+    }
+
     /// Build artifacts with the specified profile
     pub fn with_profile(mut self, profile: Option<String>) -> Self {
         self.profile = profile;
         self
     }
 
+    /// Require Cargo.lock is up to date
+    pub fn with_locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Run without accessing the network
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Require Cargo.lock and cache are up to date.
+    /// This is equivalent to enabling both `with_locked` and `with_offline`.
+    pub fn with_frozen(mut self, frozen: bool) -> Self {
+        self.frozen = frozen;
+        self
+    }
+
+    /// Do not abort the build as soon as there is an error.
+    /// This is passed on to cargo and when multiple targets are built, the remaining targets are still built after one fails.
+    /// A summary of which targets succeeded and failed is then printed and the build fails if any target failed.
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// The number of parallel jobs cargo builds with, passed on as `--jobs`.
+    /// Defaults to cargo's own default of the number of CPUs.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Features to activate, passed to cargo unchanged so package qualified `pkg/feature` and `dep:name` features can be used.
+    /// e.g. `with_features(vec!["webgl".to_owned(), "renderer/vulkan".to_owned()])`
+    pub fn with_features(mut self, features: Vec<String>) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Rebuild the standard library crates with the same settings as the wasm build via `-Z build-std`.
+    /// e.g. `with_build_std(vec!["std".to_owned(), "panic_abort".to_owned()])`
+    ///
+    /// This is commonly combined with `-C opt-level=z` or `panic = "abort"` to produce smaller wasm files.
+    /// Requires a nightly toolchain and the `rust-src` component, passing an empty list disables it.
+    pub fn with_build_std(mut self, build_std: Vec<String>) -> Self {
+        self.build_std = build_std;
+        self
+    }
+
     // Pass raw cargo flags for anything not already handled by `RunWasm::with_*` methods.
     // Warning: Do not use this for any flags already handled by `RunWasm::with_*` or run-wasm's internal invariants will be broken.
     // e.g. `with_cargo_build_args(vec!["--color", "always", "--locked"])`
@@ -230,187 +1133,3260 @@ impl RunWasm {
         self
     }
 
-    /// Makes the dev server listen on host (default 'localhost')
-    pub fn with_host(mut self, host: Option<String>) -> Self {
-        self.host = host;
+    /// Generate the js glue by running the wasm-bindgen CLI at `wasm_bindgen` instead of the wasm-bindgen library built into run-wasm.
+    ///
+    /// wasm-bindgen requires the CLI version to exactly match the version of the `wasm-bindgen` crate used by the project.
+    /// Use this when the project depends on a different version than the one run-wasm was released with,
+    /// e.g. after `cargo install wasm-bindgen-cli --version 0.2.87`
+    pub fn with_external_bindgen(mut self, wasm_bindgen: PathBuf) -> Self {
+        self.external_bindgen = Some(wasm_bindgen);
         self
     }
 
-    /// Makes the dev server listen on port (default '8000')
-    pub fn with_port(mut self, port: Option<String>) -> Self {
-        self.port = port;
+    /// Replace the bodies of the functions matching the regex `patterns` with an `unreachable` instruction by running `wasm-snip`
+    /// on the wasm generated by wasm-bindgen, e.g. to remove formatting or panicking code that is known to never run.
+    ///
+    /// If a snipped function is actually called the app will trap at runtime, so only snip code that is truly dead.
+    /// run-wasm does not run wasm-opt, so run it afterwards to remove the code that only the snipped functions used.
+    /// Requires `cargo install wasm-snip`, a warning is printed and the wasm left unchanged if it is not installed.
+    pub fn with_snip(mut self, patterns: Vec<String>) -> Self {
+        self.snip = patterns;
         self
     }
 
-    /// Launch run-wasm
-    pub fn run(self) -> Result<(), String> {
-        let binary_name = match self
-            .example
-            .as_ref()
-            .or(self.bin.as_ref())
-            .or(self.package.as_ref())
-        {
-            Some(name) => name.to_owned(),
-            None => {
-                return Err("Need to use at least one of `--package NAME`, `--example NAME` `--bin NAME`.\nRun cargo run-wasm --help for more info.".to_owned());
-            }
-        };
+    /// When a debug build produces a wasm file larger than this many bytes, print a hint suggesting a release build.
+    /// Defaults to 10MB, `None` disables the hint.
+    pub fn with_size_warning_threshold(mut self, size_warning_threshold: Option<u64>) -> Self {
+        self.size_warning_threshold = size_warning_threshold;
+        self
+    }
 
-        let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    /// Fail the build if the wasm imports anything that is not provided by the wasm-bindgen js glue.
+    /// Such imports, e.g. from WASI or C code expecting an `env` module, only fail once the page loads the wasm.
+    /// Disabled by default.
+    pub fn with_validate_imports(mut self, validate_imports: bool) -> Self {
+        self.validate_imports = validate_imports;
+        self
+    }
 
-        let CargoDirectories {
-            workspace_root,
-            target_directory,
-        } = CargoDirectories::new(&cargo);
-        let target_target = target_directory.join("wasm-examples-target");
-        let mut cargo_args = vec![
-            "build".as_ref(),
-            "--target".as_ref(),
-            "wasm32-unknown-unknown".as_ref(),
-            // It is common to setup a faster linker such as mold or lld to run for just your native target.
-            // It cant be set for wasm as wasm doesnt support building with these linkers.
-            // This results in a separate rustflags value for native and wasm builds.
-            // Currently rust triggers a full rebuild every time the rustflags value changes.
-            //
-            // Therefore we have this hack where we use a different target dir for wasm builds to avoid constantly triggering full rebuilds.
-            // When this issue is resolved we might be able to remove this hack: https://github.com/rust-lang/cargo/issues/8716
-            "--target-dir".as_ref(),
-            target_target.as_os_str(),
-        ];
+    /// Demangle the rust symbol names in the wasm output, making stack traces and profiles readable.
+    /// Enabled by default, matching wasm-bindgen.
+    pub fn with_demangle(mut self, demangle: bool) -> Self {
+        self.demangle = demangle;
+        self
+    }
 
-        if let Some(package) = self.package.as_ref() {
-            cargo_args.extend([OsStr::new("--package"), package.as_ref()]);
-        }
-        if let Some(example) = self.example.as_ref() {
-            cargo_args.extend([OsStr::new("--example"), example.as_ref()]);
-        }
-        if let Some(bin) = self.bin.as_ref() {
-            cargo_args.extend([OsStr::new("--bin"), bin.as_ref()]);
-        }
-        if let Some(profile) = self.profile.as_ref() {
-            cargo_args.extend([OsStr::new("--profile"), profile.as_ref()]);
-        }
+    /// Output the js of linked modules, e.g. `#[wasm_bindgen(module = "/js/foo.js")]`, as separate files instead of inlining them into the glue.
+    /// Disabled by default, matching wasm-bindgen.
+    pub fn with_split_linked_modules(mut self, split_linked_modules: bool) -> Self {
+        self.split_linked_modules = split_linked_modules;
+        self
+    }
 
-        cargo_args.extend(self.cargo_build_args.iter().map(OsStr::new));
-        let status = Command::new(&cargo)
-            .current_dir(&workspace_root)
-            .args(&cargo_args)
-            .status()
-            .unwrap();
-        if !status.success() {
-            // We can return without printing anything because cargo will have already displayed an appropriate error.
-            return Err("Failed due to cargo error".to_owned());
-        }
+    /// Leave the default path of the wasm file out of the generated js glue, so the glue no longer assumes it is next to the wasm file.
+    /// The generated page passes the wasm path to `init` relative to the page instead, allowing the js to be hosted elsewhere.
+    /// Disabled by default.
+    pub fn with_omit_default_module_path(mut self, omit_default_module_path: bool) -> Self {
+        self.omit_default_module_path = omit_default_module_path;
+        self
+    }
 
-        let profile_dir_name = match self.profile.as_deref() {
-            Some("dev") => "debug",
-            Some(profile) => profile,
-            None => "debug",
-        };
+    /// Control when the js glue generated by wasm-bindgen uses `TextEncoder.encodeInto` to pass strings to wasm.
+    /// Defaults to the wasm-bindgen default, which is `EncodeInto::Test`.
+    pub fn with_encode_into(mut self, encode_into: EncodeInto) -> Self {
+        self.encode_into = Some(encode_into);
+        self
+    }
 
-        // run wasm-bindgen on wasm file output by cargo, write to the destination folder
-        let target_profile = target_target
-            .join("wasm32-unknown-unknown")
-            .join(profile_dir_name);
-        let wasm_source = if self.example.is_some() {
-            target_profile.join("examples")
-        } else {
-            target_profile
-        }
-        .join(format!("{}.wasm", binary_name));
+    /// Print the generated index.html of each target after building, to help debug problems with the page itself.
+    pub fn with_echo_html(mut self, echo_html: bool) -> Self {
+        self.echo_html = echo_html;
+        self
+    }
 
-        if !wasm_source.exists() {
-            return Err("There is no binary at {wasm_source:?}, maybe you used `--package NAME` on a package that has no binary?".to_owned());
-        }
+    /// Skip cargo and wasm-bindgen and only regenerate the index.html, reusing the wasm and js from the previous build.
+    /// This is much faster when only the css, init script or other page settings have changed.
+    /// Targets that have not been built before are built as usual.
+    pub fn with_html_only(mut self, html_only: bool) -> Self {
+        self.html_only = html_only;
+        self
+    }
 
-        let example_dest = target_directory.join("wasm-examples").join(&binary_name);
-        std::fs::create_dir_all(&example_dest).unwrap();
-        let mut bindgen = wasm_bindgen_cli_support::Bindgen::new();
-        bindgen
-            .web(true)
-            .unwrap()
-            .omit_default_module_path(false)
-            .input_path(&wasm_source)
-            .generate(&example_dest)
-            .unwrap();
+    /// Embed the wasm and its js glue into the generated index.html, so that the page works as a single self contained file.
+    /// Useful for sharing a demo as one file or hosting it somewhere that only accepts html.
+    ///
+    /// The wasm is base64 encoded so the page is about a third larger than the wasm itself.
+    /// Crates that use wasm-bindgen js snippets are not supported.
+    pub fn with_inline_wasm(mut self, inline_wasm: bool) -> Self {
+        self.inline_wasm = inline_wasm;
+        self
+    }
 
-        // process template index.html and write to the destination folder
-        let index_template = include_str!("index.template.html");
-        let index_processed = index_template
-            .replace("{{name}}", &binary_name)
-            // This is fine because a replaced {{name}} cant contain `{{css}} ` due to `{` not being valid in a crate name
-            .replace("{{css}}", &self.css);
-        std::fs::write(example_dest.join("index.html"), index_processed).unwrap();
-
-        if !self.build_only {
-            let host = self.host.unwrap_or_else(|| "localhost".into());
-            let port = self
-                .port
-                .unwrap_or_else(|| "8000".into())
-                .parse()
-                .expect("Port should be an integer");
-
-            // run webserver on destination folder
-            println!("\nServing `{}` on http://{}:{}", binary_name, host, port);
-            devserver_lib::run(
-                &host,
-                port,
-                example_dest.as_os_str().to_str().unwrap(),
-                false,
-                "",
-            );
-        }
+    /// Strip the comments, indentation and blank lines from the js glue generated by wasm-bindgen, roughly halving its size.
+    /// Disabled by default. Complements running wasm-opt on the wasm when producing a bundle for deployment.
+    pub fn with_minify_js(mut self, minify_js: bool) -> Self {
+        self.minify_js = minify_js;
+        self
+    }
 
-        Ok(())
+    /// Write a `.js.map` source map next to the minified js glue, so that browser devtools show the original glue when debugging.
+    /// The map is line level and only has an effect together with `with_minify_js`, as otherwise the glue is served unmodified.
+    /// Has no effect with `with_inline_wasm`, as the js is then not written as a separate file.
+    /// Disabled by default to avoid the extra file.
+    pub fn with_source_maps(mut self, source_maps: bool) -> Self {
+        self.source_maps = source_maps;
+        self
     }
-}
 
-/// High-level function that can be called as your entire run-wasm application.
-///
-/// It will:
-/// 1. Get CLI args from env
-/// 2. Compile the rust project to wasm
-/// 3. Run wasm-bindgen
-/// 4. Generate an index.html that runs the wasm
-/// 5. Launch a tiny webserver to serve index.html + your wasm
-///
-/// It will block forever to keep the webserver running until killed with ctrl-c or similar
-///
-/// The css argument will be included directly into a `<style type="text/css"></style>` element in the generated page.
-/// By default the body element will include some margin, so for full page apps you will want to remove that by calling like:
-/// ```no_run
-///     cargo_run_wasm::run_wasm_cli_with_css("body { margin: 0px; }");
+    /// After building, print the paths of the generated files as `KEY=value` lines that can be sourced by a shell script:
+    /// ```text
+    /// WASM=target/wasm-examples/name/name_bg.wasm
+    /// JS=target/wasm-examples/name/name.js
+    /// HTML=target/wasm-examples/name/index.html
+    /// DIR=target/wasm-examples/name
+    /// ```
+    /// When multiple targets are built, `DIR` and `HTML` refer to the root directory and each target's paths are prefixed with its name e.g. `NAME_WASM`.
+    pub fn with_print_paths(mut self, print_paths: bool) -> Self {
+        self.print_paths = print_paths;
+        self
+    }
+
+    /// Select where the wasm is run (default `Runtime::Browser`).
+    ///
+    /// With `Runtime::Node` or `Runtime::Deno` wasm-bindgen generates bindings for that runtime and,
+    /// instead of starting the dev server, `run()` executes the generated module with it.
+    /// If the module exits with a failure then the process exits with the same exit code.
+    /// This is useful for running examples headlessly, e.g. in CI.
+    pub fn with_runtime(mut self, runtime: Runtime) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// After building, zip up the generated files and the files of any `with_static_dir` directories into a single archive at `bundle`.
+    /// Any files written by the `with_post_build` hook are included.
+    /// When multiple targets are built, only the targets built by this run are included alongside the page linking to them.
+    ///
+    /// Combine with `with_build_only` to produce an archive ready for uploading to a static host without starting the dev server.
+    pub fn with_bundle(mut self, bundle: PathBuf) -> Self {
+        self.bundle = Some(bundle);
+        self
+    }
+
+    /// Write the generated files into `output_dir` instead of `target/wasm-examples/NAME`.
+    /// When multiple targets are built each one is written to its own subdirectory of `output_dir`.
+    ///
+    /// Combine with `with_build_only` to feed a static server you run yourself.
+    /// Note that `with_clean_output` deletes the contents of the directory a target is written to, which is `output_dir` itself for a single target.
+    pub fn with_output_dir(mut self, output_dir: PathBuf) -> Self {
+        self.output_dir = Some(output_dir);
+        self
+    }
+
+    /// The directory each target's files are written to a subdirectory of, e.g. a `dist` directory picked up by deployment tooling.
+    /// Defaults to `target/wasm-examples`. Ignored when `with_output_dir` is used.
+    pub fn with_examples_base_dir(mut self, examples_base_dir: PathBuf) -> Self {
+        self.examples_base_dir = Some(examples_base_dir);
+        self
+    }
+
+    /// Build into the regular cargo target directory instead of a separate wasm specific target directory.
+    ///
+    /// By default run-wasm builds into `target/wasm-examples-target` because native and wasm builds commonly use different rustflags
+    /// and cargo triggers a full rebuild every time the rustflags change.
+    /// If your project does not configure any rustflags then enabling this avoids keeping a duplicate copy of all build artifacts around.
+    pub fn with_shared_target_dir(mut self, shared_target_dir: bool) -> Self {
+        self.shared_target_dir = shared_target_dir;
+        self
+    }
+
+    /// Delete the output directory of each target before generating its files.
+    /// This ensures no stale files from a previous build of the same target are left behind, e.g. assets that have since been removed.
+    pub fn with_clean_output(mut self, clean_output: bool) -> Self {
+        self.clean_output = clean_output;
+        self
+    }
+
+    /// Set the `RUSTFLAGS` used for the wasm build, without affecting native builds.
+    /// e.g. `with_wasm_rustflags(Some("-C target-feature=+atomics,+bulk-memory".to_owned()))`
+    ///
+    /// This replaces any rustflags configured via the `RUSTFLAGS` env var or cargo config.
+    /// Since wasm builds use their own target directory, changing these flags will not trigger a rebuild of native artifacts.
+    /// However if `with_shared_target_dir` is enabled, alternating between native and wasm builds will trigger full rebuilds every time.
+    pub fn with_wasm_rustflags(mut self, wasm_rustflags: Option<String>) -> Self {
+        self.wasm_rustflags = wasm_rustflags;
+        self
+    }
+
+    /// Enable wasm target features for the wasm build by adding `-C target-feature=...` to its `RUSTFLAGS`.
+    /// e.g. `with_target_features(vec!["simd128".to_owned()])`
+    ///
+    /// A feature may be prefixed with `+` to enable it or `-` to disable it, features without a prefix are enabled.
+    /// The flags are added to those set by `with_wasm_rustflags`, otherwise the `CARGO_ENCODED_RUSTFLAGS` or `RUSTFLAGS` env var,
+    /// otherwise the `target.wasm32-unknown-unknown.rustflags` of the cargo config.
+    /// In the last case a `build.rustflags` config is no longer used, as cargo ignores it when target specific rustflags are set.
+    ///
+    /// Keep in mind that older browsers do not support newer features such as `simd128`, and fail to load wasm that uses them.
+    pub fn with_target_features(mut self, target_features: Vec<String>) -> Self {
+        self.target_features = target_features;
+        self
+    }
+
+    /// Makes the dev server listen on host (default 'localhost')
+    pub fn with_host(mut self, host: Option<String>) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Makes the dev server listen on port (default '8000')
+    pub fn with_port(mut self, port: Option<String>) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Makes the dev server listen on `addr`, the typed equivalent of setting both `with_host` and `with_port`.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// # use std::net::{Ipv4Addr, SocketAddr};
+    /// RunWasm::new()
+    ///     .with_addr(SocketAddr::from((Ipv4Addr::LOCALHOST, 8080)))
+    ///     .run();
+    /// ```
+    pub fn with_addr(mut self, addr: SocketAddr) -> Self {
+        self.host = Some(addr.ip().to_string());
+        self.port = Some(addr.port().to_string());
+        self
+    }
+
+    /// Only listen on an address of this family when the host resolves to both IPv4 and IPv6 addresses.
+    /// By default the first address the host resolves to is used.
+    pub fn with_address_family(mut self, address_family: Option<AddressFamily>) -> Self {
+        self.address_family = address_family;
+        self
+    }
+
+    /// Controls the `Cache-Control` headers sent by the dev server (default `CachePolicy::NoCache`)
+    pub fn with_cache_policy(mut self, cache_policy: CachePolicy) -> Self {
+        self.cache_policy = cache_policy;
+        self
+    }
+
+    /// Makes the dev server respond with index.html for any path that does not match a file.
+    /// This is required for single page apps that perform routing client side, so that reloading the page on a route still loads the app.
+    pub fn with_spa_fallback(mut self, spa_fallback: bool) -> Self {
+        self.spa_fallback = spa_fallback;
+        self
+    }
+
+    /// Print the method, path and response status of every request the dev server receives.
+    /// Useful for figuring out which files the browser actually requested when the app fails to load.
+    pub fn with_access_log(mut self, access_log: bool) -> Self {
+        self.access_log = access_log;
+        self
+    }
+
+    /// Add a script to the page that reloads it when the generated files change.
+    /// The dev server polls the output directory for changes and notifies the page over a websocket at `/__run_wasm_live_reload`.
+    /// The page also reloads when it reconnects after the dev server restarted, so rebuilding with e.g. `cargo watch -x run-wasm` works too.
+    pub fn with_live_reload(mut self, live_reload: bool) -> Self {
+        self.live_reload = live_reload;
+        self
+    }
+
+    /// Open the page in the default browser once the dev server is listening.
+    /// This is skipped when a CI environment is detected via the `CI` or `GITHUB_ACTIONS` env vars, as there is no browser to open.
+    pub fn with_open(mut self, open: bool) -> Self {
+        self.open = open;
+        self
+    }
+
+    /// Open the page in the default browser even when a CI environment is detected, implies `with_open(true)`
+    pub fn with_force_open(mut self, force_open: bool) -> Self {
+        self.force_open = force_open;
+        self
+    }
+
+    /// Only print warnings and errors, silencing run-wasm's informational output such as the dev server banner, hints and the access log.
+    /// `--quiet` is also passed on to cargo.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Print the cargo command run-wasm runs, and pass `--verbose` on to cargo `verbose` times.
+    pub fn with_verbose(mut self, verbose: u8) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Shut down the dev server and return from `run()` once the timeout has elapsed, instead of serving forever.
+    /// This is useful for smoke testing in CI, where a headless browser loads the page while the server is up.
+    pub fn with_serve_timeout(mut self, serve_timeout: Option<Duration>) -> Self {
+        self.serve_timeout = serve_timeout;
+        self
+    }
+
+    /// Forward any request for `path_prefix` or a path below it to the http server at `target`, e.g. `/api` forwards `/api/users` but not `/apiary`.
+    /// This allows the wasm app to talk to a local backend, including over websockets, without running into cross origin issues.
+    /// The request path is forwarded unchanged, including the prefix.
+    /// Can be called multiple times to add multiple proxies.
+    /// `target` must be an `http://` url without a path, otherwise the error is reported when building.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// RunWasm::new()
+    ///     .with_proxy("/api", "http://localhost:3000")
+    ///     .run();
+    /// ```
+    pub fn with_proxy(mut self, path_prefix: &str, target: &str) -> Self {
+        // Any problem is reported when building, so the builder does not need to return a Result
+        match proxy_address(target) {
+            Ok(address) => self
+                .proxies
+                .push((path_prefix.to_owned(), address.to_owned())),
+            Err(err) => self.proxy_errors.push(err),
+        }
+        self
+    }
+
+    /// Serve the files in `static_dir` alongside the generated files, e.g. for images and other assets loaded by the app.
+    ///
+    /// Can be called multiple times to serve multiple directories.
+    /// When the same file exists in multiple directories, the directory added first takes precedence.
+    /// Files in static directories must not have the same path as any of the generated files.
+    pub fn with_static_dir(mut self, static_dir: PathBuf) -> Self {
+        self.static_dirs.push(static_dir);
+        self
+    }
+
+    /// Make the dev server respond with 403 to requests whose `Host` header is not one of `allowed_hosts`.
+    /// This protects against DNS rebinding attacks, where a malicious website points its own domain at the dev server to read its files.
+    /// `localhost` and ip addresses are always allowed, as they cannot be rebound.
+    /// When empty, which is the default, requests for any host are accepted.
+    pub fn with_allowed_hosts(mut self, allowed_hosts: Vec<String>) -> Self {
+        self.allowed_hosts = allowed_hosts;
+        self
+    }
+
+    /// Make the dev server respond to requests for `path` with the contents of `file`.
+    /// Routes take priority over the generated files, which makes them useful for mocking endpoints the wasm app fetches.
+    /// Can be called multiple times to add multiple routes.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// RunWasm::new()
+    ///     .with_route("/api/user.json", "mocks/user.json".into())
+    ///     .run();
+    /// ```
+    pub fn with_route(mut self, path: &str, file: PathBuf) -> Self {
+        self.routes.push((path.to_owned(), file));
+        self
+    }
+
+    /// Serve files with the extension `extension` with the given `Content-Type`, overriding the dev server's built in mapping.
+    /// Can be called multiple times to register multiple extensions.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// RunWasm::new()
+    ///     .with_mime("glb", "model/gltf-binary")
+    ///     .run();
+    /// ```
+    pub fn with_mime(mut self, extension: &str, content_type: &str) -> Self {
+        self.mime_types.push((
+            extension.trim_start_matches('.').to_owned(),
+            content_type.to_owned(),
+        ));
+        self
+    }
+
+    /// Write `contents` to `path` within the output directory of each target, e.g. a config json generated by an xtask for the app to fetch.
+    /// Parent directories in `path` are created as needed.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// RunWasm::new()
+    ///     .with_file("config/settings.json", br#"{ "debug": true }"#.to_vec())
+    ///     .run();
+    /// ```
+    pub fn with_file(mut self, path: &str, contents: Vec<u8>) -> Self {
+        self.files.push((path.to_owned(), contents));
+        self
+    }
+
+    /// Serve on an already bound listener instead of binding to the host and port.
+    /// Binding to port 0 and passing the listener in lets a test harness learn the port the dev server is using without racing another process for it.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let port = listener.local_addr().unwrap().port();
+    /// RunWasm::new()
+    ///     .with_listener(listener)
+    ///     .run();
+    /// ```
+    pub fn with_listener(mut self, listener: TcpListener) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    /// Make the dev server listen on a unix domain socket at `path` instead of a tcp port, for sandboxes that do not allow binding ports.
+    /// Browsers can not connect to a unix socket directly, so this is intended for use behind a proxy or with automated clients e.g. `curl --unix-socket`.
+    /// The path of the socket is passed to the `with_on_ready_command` command in the `RUN_WASM_UNIX_SOCKET` env var.
+    #[cfg(unix)]
+    pub fn with_unix_socket(mut self, path: PathBuf) -> Self {
+        self.unix_socket = Some(path);
+        self
+    }
+
+    /// Run a command once the dev server is listening, with the url of the served page in the `RUN_WASM_URL` env var.
+    /// The dev server is shut down and `run()` returns once the command exits, returning an error if the command failed.
+    /// If the serve timeout elapses first then the command is killed.
+    ///
+    /// This allows run-wasm to drive an end to end test in CI: build, serve, run a browser test runner against the page and tear down.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// RunWasm::new()
+    ///     .with_on_ready_command(vec!["npx".to_owned(), "playwright".to_owned(), "test".to_owned()])
+    ///     .run();
+    /// ```
+    pub fn with_on_ready_command(mut self, on_ready_command: Vec<String>) -> Self {
+        self.on_ready_command = on_ready_command;
+        self
+    }
+
+    /// Run custom logic after all files have been generated but before the dev server is started.
+    /// The closure is given the directory containing the generated files, any files written to it will be served.
+    /// Returning an error aborts run-wasm with that error.
+    /// The closure must be `Send + Sync` so that `RunWasm` can still be handed to another thread.
+    /// ```no_run
+    /// # use cargo_run_wasm::RunWasm;
+    /// RunWasm::new()
+    ///     .with_post_build(|dir| {
+    ///         std::fs::write(dir.join("config.json"), "{}").map_err(|e| e.to_string())
+    ///     })
+    ///     .run();
+    /// ```
+    pub fn with_post_build<F: FnOnce(&Path) -> Result<(), String> + Send + Sync + 'static>(
+        mut self,
+        post_build: F,
+    ) -> Self {
+        self.post_build = Some(Box::new(post_build));
+        self
+    }
+
+    /// Compile the rust project to wasm, run wasm-bindgen and generate an index.html
+    ///
+    /// This does everything `run()` does except for launching the webserver.
+    /// The returned [`BuildOutput`] describes where the generated files were written to,
+    /// which is useful for post-processing the artifacts e.g. zipping or deploying them.
+    pub fn build(&self) -> Result<BuildOutput, RunWasmError> {
+        let errors = self.settings_errors();
+        if !errors.is_empty() {
+            return Err(RunWasmError::InvalidConfig {
+                reason: errors.join("\n\n"),
+            });
+        }
+        let mut workspace = Workspace::default();
+        let resolved = self.resolve_in(&mut workspace)?;
+        self.build_resolved(resolved, &mut workspace)
+    }
+
+    /// Build the already `resolved` targets
+    fn build_resolved(
+        &self,
+        resolved: ResolvedTargets,
+        workspace: &mut Workspace,
+    ) -> Result<BuildOutput, RunWasmError> {
+        if self
+            .target_features
+            .iter()
+            .any(|x| x.trim_start_matches('+') == "simd128")
+        {
+            println!(
+                "{} wasm built with simd128 enabled will fail to load in browsers without wasm SIMD support",
+                color::warning("warning:")
+            );
+        }
+
+        if let (Some(jobs), Ok(available)) = (self.jobs, std::thread::available_parallelism()) {
+            if jobs > available.get() {
+                println!(
+                    "{} building with {jobs} jobs but only {available} cpus are available",
+                    color::warning("warning:")
+                );
+            }
+        }
+
+        let cargo = self.cargo_executable();
+        let build_id = new_build_id();
+        let ResolvedTargets {
+            dir: examples_dir,
+            targets,
+        } = resolved;
+
+        if self.prebuilt_wasm.is_some() {
+            let target = &targets[0];
+            let output = match self.reuse_previous_build(target, &build_id) {
+                Some(output) => output.map_err(RunWasmError::from)?,
+                None => {
+                    self.generate(&target.name, &target.wasm_source, &target.dest, &build_id)?
+                }
+            };
+            return Ok(BuildOutput::single(output, build_id));
+        }
+
+        let directories = self.workspace_directories(&cargo, workspace)?;
+        let mut outputs = vec![];
+        let mut failed = vec![];
+        for target in &targets {
+            let result = match self.reuse_previous_build(target, &build_id) {
+                Some(result) => result.map_err(RunWasmError::from),
+                None => self
+                    .cargo_build(&cargo, &directories, target)
+                    .and_then(|wasm_source| {
+                        self.generate(&target.name, &wasm_source, &target.dest, &build_id)
+                    }),
+            };
+            match result {
+                Ok(output) => outputs.push(output),
+                Err(err) if self.keep_going && targets.len() > 1 => {
+                    println!(
+                        "{} Failed to build {} `{}`: {err}",
+                        color::error("error:"),
+                        target.kind.cargo_kind(),
+                        target.name
+                    );
+                    failed.push(target);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        if targets.len() == 1 {
+            return Ok(BuildOutput::single(outputs.remove(0), build_id));
+        }
+        if !failed.is_empty() {
+            // With many targets the individual errors scroll away, so recap which ones broke
+            println!(
+                "\nBuilt {} of {} targets:",
+                targets.len() - failed.len(),
+                targets.len()
+            );
+            for target in &targets {
+                let status = if failed.iter().any(|x| x.name == target.name) {
+                    color::error("failed")
+                } else {
+                    color::status("ok    ")
+                };
+                println!("  {status} {} `{}`", target.kind.cargo_kind(), target.name);
+            }
+            return Err(RunWasmError::TargetsFailed {
+                failed: failed
+                    .iter()
+                    .map(|target| format!("{} `{}`", target.kind.cargo_kind(), target.name))
+                    .collect(),
+                total: targets.len(),
+            });
+        }
+
+        // Multiple targets are served from the shared parent directory with an index.html linking to each of them.
+        let links: Vec<String> = outputs
+            .iter()
+            .map(|output| {
+                let name = template::escape_attribute(&output.name);
+                format!(r#"<li><a href="./{name}/">{name}</a></li>"#)
+            })
+            .collect();
+        let listing = template::render_listing(&links.join("\n    "));
+        let html = examples_dir.join(&self.index_file);
+        std::fs::write(&html, listing).map_err(|err| format!("Failed to write {html:?}: {err}"))?;
+
+        Ok(BuildOutput {
+            dir: examples_dir,
+            html,
+            build_id,
+            targets: outputs,
+        })
+    }
+
+    /// Determine which targets will be built and where their files will be written, without building anything.
+    ///
+    /// This uses the same logic as `build()` and `run()`, which makes it useful for tooling that needs to know where the output will be in advance.
+    pub fn resolve(&self) -> Result<ResolvedTargets, RunWasmError> {
+        self.resolve_in(&mut Workspace::default())
+    }
+
+    fn resolve_in(&self, workspace: &mut Workspace) -> Result<ResolvedTargets, RunWasmError> {
+        let cargo = self.cargo_executable();
+
+        if let Some(prebuilt_wasm) = &self.prebuilt_wasm {
+            let name = validate_prebuilt_wasm(prebuilt_wasm)
+                .map_err(|reason| RunWasmError::InvalidConfig { reason })?;
+            // A prebuilt wasm file may not be part of a cargo project, in which case output next to the wasm file instead.
+            if let Some(output_dir) = &self.output_dir {
+                return Ok(ResolvedTargets {
+                    dir: output_dir.clone(),
+                    targets: vec![ResolvedTarget {
+                        dest: output_dir.clone(),
+                        name,
+                        wasm_source: prebuilt_wasm.clone(),
+                        kind: TargetKind::Bin,
+                    }],
+                });
+            }
+            let dir = match (
+                &self.examples_base_dir,
+                self.workspace_directories(&cargo, workspace),
+            ) {
+                (Some(examples_base_dir), _) => examples_base_dir.clone(),
+                (None, Ok(directories)) => directories.target_directory.join("wasm-examples"),
+                (None, Err(_)) => prebuilt_wasm
+                    .parent()
+                    .unwrap_or_else(|| Path::new("."))
+                    .join("wasm-examples"),
+            };
+            let target = ResolvedTarget {
+                dest: dir.join(&name),
+                name,
+                wasm_source: prebuilt_wasm.clone(),
+                kind: TargetKind::Bin,
+            };
+            return Ok(ResolvedTargets {
+                dir,
+                targets: vec![target],
+            });
+        }
+
+        let directories = self.workspace_directories(&cargo, workspace)?;
+        let dir = match (&self.output_dir, &self.examples_base_dir) {
+            (Some(output_dir), _) => output_dir.clone(),
+            (None, Some(examples_base_dir)) => examples_base_dir.clone(),
+            (None, None) => directories.target_directory.join("wasm-examples"),
+        };
+        let profile_dir = self
+            .wasm_target_dir(&directories)
+            .join("wasm32-unknown-unknown")
+            .join(match self.profile.as_deref() {
+                None | Some("dev") | Some("test") => "debug",
+                Some("bench") => "release",
+                Some(profile) => profile,
+            });
+        let targets = self
+            .resolve_targets(&cargo, &directories.workspace_root, workspace)
+            .map_err(|reason| RunWasmError::InvalidConfig { reason })?;
+        // A single target is written straight into the output dir, as that is where the user expects to find it
+        let single_output_dir = self.output_dir.is_some() && targets.len() == 1;
+        let targets = targets
+            .into_iter()
+            .map(|target| {
+                let file_name = wasm_file_name(&target.name);
+                let wasm_source = match target.kind {
+                    TargetKind::Example => profile_dir.join("examples").join(file_name),
+                    TargetKind::Bin | TargetKind::Package => profile_dir.join(file_name),
+                };
+                ResolvedTarget {
+                    dest: if single_output_dir {
+                        dir.clone()
+                    } else {
+                        dir.join(&target.name)
+                    },
+                    name: target.name,
+                    wasm_source,
+                    kind: target.kind,
+                }
+            })
+            .collect();
+        Ok(ResolvedTargets { dir, targets })
+    }
+
+    /// Check that the configuration can be run, without building anything.
+    ///
+    /// This checks that the wasm target is installed, the selected package, targets and profile exist, the css passes the strict check if enabled,
+    /// the port is valid and any files referenced by the configuration exist.
+    /// Every problem found is reported together instead of stopping at the first one.
+    ///
+    /// `run()` calls this before building, but it can also be called directly to fail fast, e.g. before an xtask does other expensive work.
+    pub fn validate(&self) -> Result<(), RunWasmError> {
+        self.validate_in(&mut Workspace::default()).map(|_| ())
+    }
+
+    /// Validate the configuration, returning the resolved targets so that they do not need to be resolved again for the build
+    fn validate_in(&self, workspace: &mut Workspace) -> Result<ResolvedTargets, RunWasmError> {
+        let mut errors = self.settings_errors();
+        let mut resolved = None;
+
+        if self.prebuilt_wasm.is_none() {
+            let cargo = self.cargo_executable();
+            match self.workspace_directories(&cargo, workspace) {
+                Ok(directories) => {
+                    // build-std builds the standard library from source, so the prebuilt target is not needed
+                    if self.build_std.is_empty()
+                        && !is_wasm_target_installed(
+                            &directories.workspace_root,
+                            self.toolchain.as_deref(),
+                        )
+                    {
+                        errors.push("The wasm32-unknown-unknown target is not installed.\nInstall it with `rustup target add wasm32-unknown-unknown`".to_owned());
+                    }
+                    match self.resolve_in(workspace) {
+                        Ok(targets) => {
+                            if let Err(err) = self.validate_targets(
+                                &cargo,
+                                &directories.workspace_root,
+                                &targets.targets,
+                                workspace,
+                            ) {
+                                errors.push(err);
+                            }
+                            resolved = Some(targets);
+                        }
+                        Err(err) => errors.push(err.to_string()),
+                    }
+                    if let Some(profile) = &self.profile {
+                        if let Err(err) = validate_profile(profile, &directories.workspace_root) {
+                            errors.push(err);
+                        }
+                    }
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(RunWasmError::InvalidConfig {
+                reason: errors.join("\n\n"),
+            });
+        }
+        match resolved {
+            Some(resolved) => Ok(resolved),
+            None => self.resolve_in(workspace),
+        }
+    }
+
+    /// Checks of the settings that do not need to ask cargo anything, cheap enough to perform on every build
+    fn settings_errors(&self) -> Vec<String> {
+        let mut errors = vec![];
+        if self.css_strict {
+            if let Err(err) = template::validate_css_strict(&self.css) {
+                errors.push(err);
+            }
+        }
+        for feature in &self.target_features {
+            let name = feature.trim_start_matches(&['+', '-'][..]);
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                errors.push(format!("Invalid target feature `{feature}`"));
+            }
+        }
+        if self.unix_socket.is_some()
+            && (self.host.is_some() || self.port.is_some() || self.address_family.is_some())
+        {
+            errors.push(
+                "The host, port and address family can not be set when serving on a unix socket"
+                    .to_owned(),
+            );
+        }
+        if self.listener.is_some()
+            && (self.host.is_some()
+                || self.port.is_some()
+                || self.address_family.is_some()
+                || self.unix_socket.is_some())
+        {
+            errors.push(
+                "The host, port, address family and unix socket can not be set when a listener is provided"
+                    .to_owned(),
+            );
+        }
+        if self.jobs == Some(0) {
+            errors.push("The number of jobs must be greater than 0".to_owned());
+        }
+        if let Some(port) = &self.port {
+            if port.parse::<u16>().is_err() {
+                errors.push(format!("The port `{port}` is not a valid port number"));
+            }
+        }
+        if let Some(prebuilt_wasm) = &self.prebuilt_wasm {
+            if let Err(err) = validate_prebuilt_wasm(prebuilt_wasm) {
+                errors.push(err);
+            }
+        }
+        if let Some(err) = &self.package_path_error {
+            errors.push(err.clone());
+        }
+        errors.extend(self.proxy_errors.iter().cloned());
+        if self.package_path.is_some() {
+            if !self.packages.is_empty() {
+                errors.push(
+                    "A package can be selected by name or by path but not both, remove `--package` or `--path` to continue"
+                        .to_owned(),
+                );
+            }
+            if self
+                .cargo_build_args
+                .iter()
+                .any(|x| x == "--manifest-path" || x.starts_with("--manifest-path="))
+            {
+                errors.push(
+                    "`--path` already sets the manifest path, remove `--manifest-path` or `--path` to continue"
+                        .to_owned(),
+                );
+            }
+        }
+        for static_dir in &self.static_dirs {
+            if !static_dir.is_dir() {
+                errors.push(format!(
+                    "The static directory {static_dir:?} does not exist"
+                ));
+            }
+        }
+        for (path, _) in &self.files {
+            if relative_file_path(path).is_none() {
+                errors.push(format!(
+                    "The file path `{path}` must be relative and stay within the output directory"
+                ));
+            }
+        }
+        for (path, file) in &self.routes {
+            if !file.is_file() {
+                errors.push(format!(
+                    "The file {file:?} for the route `{path}` does not exist"
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Check that the package and targets selected exist in the workspace
+    fn validate_targets(
+        &self,
+        cargo: &Path,
+        workspace_root: &Path,
+        targets: &[ResolvedTarget],
+        workspace: &mut Workspace,
+    ) -> Result<(), String> {
+        let metadata = self.workspace_metadata(cargo, workspace_root, workspace)?;
+        // Report every missing package and target at once, as with multiple packages there can be several
+        let mut errors = vec![];
+        for package in &self.packages {
+            if !metadata.packages.iter().any(|x| &x.name == package) {
+                errors.push(format!(
+                    "The package `{package}` does not exist in the workspace"
+                ));
+            }
+        }
+        for target in targets {
+            let kind = target.kind.cargo_kind();
+            match target.kind {
+                TargetKind::Package => {
+                    if metadata.targets(Some(&target.name), kind).is_empty() {
+                        errors.push(format!(
+                            "The package `{}` does not have a bin target",
+                            target.name
+                        ));
+                    }
+                }
+                _ => {
+                    let available = metadata.targets(self.package(), kind);
+                    if !available.contains(&target.name.as_str()) {
+                        errors.push(format!(
+                            "The {kind} `{}` does not exist, available {kind} targets are:\n{}",
+                            target.name,
+                            available.join("\n")
+                        ));
+                    }
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Print the versions of wasm-bindgen and the toolchain that run-wasm will use.
+    /// The wasm-bindgen version used by the project must match the version run-wasm generates bindings with, so this is useful to include in bug reports.
+    pub fn print_bindgen_info(&self) -> Result<(), RunWasmError> {
+        let cargo = self.cargo_executable();
+        // Outside of a cargo project there is still useful information to print
+        let workspace_root = self
+            .cargo_directories(&cargo)
+            .map(|x| x.workspace_root)
+            .ok();
+        let dir = workspace_root.as_deref().unwrap_or_else(|| Path::new("."));
+
+        match &self.external_bindgen {
+            Some(external_bindgen) => println!(
+                "wasm-bindgen: external CLI {external_bindgen:?}, {}",
+                command_version(Command::new(external_bindgen), dir)
+            ),
+            None => println!(
+                "wasm-bindgen: built in wasm-bindgen-cli-support {}",
+                wasm_bindgen_shared::version()
+            ),
+        }
+        println!(
+            "wasm-bindgen schema version: {}",
+            wasm_bindgen_shared::SCHEMA_VERSION
+        );
+        let project_version = workspace_root
+            .as_deref()
+            .and_then(|root| locked_version(root, "wasm-bindgen"));
+        println!(
+            "project wasm-bindgen: {}",
+            project_version
+                .as_deref()
+                .unwrap_or("not found in Cargo.lock")
+        );
+        println!(
+            "bindgen target: {}",
+            match self.runtime {
+                Runtime::Browser => "web",
+                Runtime::Node => "nodejs",
+                Runtime::Deno => "deno",
+            }
+        );
+        let toolchain = self.toolchain.as_deref();
+        println!("rustc: {}", command_version(rustc_command(toolchain), dir));
+        let mut cargo_command = Command::new(&cargo);
+        if let Some(toolchain) = toolchain {
+            cargo_command.env("RUSTUP_TOOLCHAIN", toolchain);
+        }
+        println!("cargo: {}", command_version(cargo_command, dir));
+        println!(
+            "wasm32-unknown-unknown installed: {}",
+            is_wasm_target_installed(dir, self.toolchain.as_deref())
+        );
+        Ok(())
+    }
+
+    /// Print every setting run-wasm will use, after applying defaults and locating the cargo directories
+    pub fn print_config(&self) -> Result<(), RunWasmError> {
+        let cargo = self.cargo_executable();
+        let directories = self.cargo_directories(&cargo)?;
+        println!("cargo: {:?}", cargo);
+        println!("toolchain: {:?}", self.toolchain);
+        println!("workspace root: {:?}", directories.workspace_root);
+        println!("target directory: {:?}", directories.target_directory);
+        println!("shared target directory: {}", self.shared_target_dir);
+        println!("clean output: {}", self.clean_output);
+        println!("prebuilt wasm: {:?}", self.prebuilt_wasm);
+        println!("packages: {:?}", self.packages);
+        println!(
+            "package path: {:?}",
+            self.package_path.as_ref().map(|x| &x.dir)
+        );
+        println!("bin: {:?}", self.bin);
+        println!("example: {:?}", self.example);
+        println!("sole bin: {}", self.sole_bin);
+        println!("sole example: {}", self.sole_example);
+        println!("all examples: {}", self.all_examples);
+        println!("profile: {}", self.profile.as_deref().unwrap_or("dev"));
+        println!("features: {:?}", self.features);
+        println!("locked: {}", self.locked);
+        println!("offline: {}", self.offline);
+        println!("frozen: {}", self.frozen);
+        println!("keep going: {}", self.keep_going);
+        println!("jobs: {:?}", self.jobs);
+        println!("build std: {:?}", self.build_std);
+        println!("wasm rustflags: {:?}", self.wasm_rustflags);
+        println!("target features: {:?}", self.target_features);
+        println!("cargo build args: {:?}", self.cargo_build_args);
+        println!("build only: {}", self.build_only);
+        println!("validate imports: {}", self.validate_imports);
+        println!("size warning threshold: {:?}", self.size_warning_threshold);
+        println!("demangle: {}", self.demangle);
+        println!("split linked modules: {}", self.split_linked_modules);
+        println!(
+            "omit default module path: {}",
+            self.omit_default_module_path
+        );
+        println!("external wasm-bindgen: {:?}", self.external_bindgen);
+        println!("encode into: {:?}", self.encode_into);
+        println!("echo html: {}", self.echo_html);
+        println!("html only: {}", self.html_only);
+        println!("inline wasm: {}", self.inline_wasm);
+        println!("minify js: {}", self.minify_js);
+        println!("snip: {:?}", self.snip);
+        println!("source maps: {}", self.source_maps);
+        println!("print paths: {}", self.print_paths);
+        println!("runtime: {:?}", self.runtime);
+        println!("bundle: {:?}", self.bundle);
+        println!("output dir: {:?}", self.output_dir);
+        println!("examples base dir: {:?}", self.examples_base_dir);
+        println!("host: {}", self.host.as_deref().unwrap_or("localhost"));
+        println!("port: {}", self.port.as_deref().unwrap_or("8000"));
+        println!("address family: {:?}", self.address_family);
+        println!("unix socket: {:?}", self.unix_socket);
+        println!(
+            "listener: {:?}",
+            self.listener.as_ref().and_then(|x| x.local_addr().ok())
+        );
+        println!("cache policy: {:?}", self.cache_policy);
+        println!("spa fallback: {}", self.spa_fallback);
+        println!("access log: {}", self.access_log);
+        println!("live reload: {}", self.live_reload);
+        println!("open: {}", self.open);
+        println!("force open: {}", self.force_open);
+        println!("quiet: {}", self.quiet);
+        println!("verbose: {}", self.verbose);
+        println!("serve timeout: {:?}", self.serve_timeout);
+        println!("proxies: {:?}", self.proxies);
+        println!("routes: {:?}", self.routes);
+        println!(
+            "files: {:?}",
+            self.files.iter().map(|(path, _)| path).collect::<Vec<_>>()
+        );
+        println!("static dirs: {:?}", self.static_dirs);
+        println!("allowed hosts: {:?}", self.allowed_hosts);
+        println!("mime types: {:?}", self.mime_types);
+        println!("on ready command: {:?}", self.on_ready_command);
+        println!("index file: {}", self.index_file);
+        println!("base path: {}", self.base_path);
+        println!("viewport meta: {}", self.viewport_meta);
+        println!("preload wasm: {}", self.preload_wasm);
+        println!("meta: {:?}", self.meta);
+        println!("head: {:?}", self.head);
+        println!("lang: {}", self.lang);
+        println!("dir: {:?}", self.dir);
+        println!(
+            "import map: {:?}",
+            self.import_map.as_ref().map(|x| x.to_string())
+        );
+        println!("fullscreen canvas: {}", self.fullscreen_canvas);
+        println!("css: {} bytes", self.css.len());
+        println!("init script: {} bytes", self.init_script.len());
+        println!("log level: {:?}", self.log_level);
+        println!("post build hook: {}", self.post_build.is_some());
+        Ok(())
+    }
+
+    /// How the rustflags of the wasm build are changed, or None to leave the user's configuration untouched
+    fn rustflags(&self) -> Option<Rustflags> {
+        let feature_flag = if self.target_features.is_empty() {
+            None
+        } else {
+            let features: Vec<String> = self
+                .target_features
+                .iter()
+                .map(|feature| {
+                    if feature.starts_with(&['+', '-'][..]) {
+                        feature.clone()
+                    } else {
+                        format!("+{feature}")
+                    }
+                })
+                .collect();
+            Some(format!("target-feature={}", features.join(",")))
+        };
+        let feature_flag = match (&self.wasm_rustflags, feature_flag) {
+            (Some(wasm_rustflags), None) => return Some(Rustflags::Env(wasm_rustflags.clone())),
+            (Some(wasm_rustflags), Some(feature_flag)) => {
+                return Some(Rustflags::Env(format!(
+                    "{wasm_rustflags} -C {feature_flag}"
+                )))
+            }
+            (None, None) => return None,
+            (None, Some(feature_flag)) => feature_flag,
+        };
+        // The env vars take priority over the cargo config, so the features have to be added to whichever is in use
+        if let Some(encoded) = env::var("CARGO_ENCODED_RUSTFLAGS")
+            .ok()
+            .filter(|x| !x.is_empty())
+        {
+            return Some(Rustflags::Encoded(format!(
+                "{encoded}\x1f-C\x1f{feature_flag}"
+            )));
+        }
+        if let Some(rustflags) = env::var("RUSTFLAGS").ok().filter(|x| !x.trim().is_empty()) {
+            return Some(Rustflags::Env(format!("{rustflags} -C {feature_flag}")));
+        }
+        Some(Rustflags::Config(format!(
+            r#"target.wasm32-unknown-unknown.rustflags=["-C", "{feature_flag}"]"#
+        )))
+    }
+
+    fn cargo_executable(&self) -> PathBuf {
+        match (&self.cargo, &self.toolchain) {
+            (Some(cargo), _) => cargo.clone(),
+            // The `CARGO` env var points at the cargo of the toolchain run-wasm is running on, so use the rustup proxy instead
+            (None, Some(_)) => PathBuf::from("cargo"),
+            (None, None) => default_cargo_executable(),
+        }
+    }
+
+    /// The package that examples and bins are selected from, or None to select from the whole workspace
+    fn package(&self) -> Option<&str> {
+        match &self.package_path {
+            Some(package_path) => Some(&package_path.name),
+            None => self.packages.first().map(|x| x.as_str()),
+        }
+    }
+
+    /// Determine which targets need to be built from the package, bin and example selection
+    fn resolve_targets(
+        &self,
+        cargo: &Path,
+        workspace_root: &Path,
+        workspace: &mut Workspace,
+    ) -> Result<Vec<BuildTarget>, String> {
+        let example_selected = self.example.is_some() || self.sole_example;
+        let bin_selected = self.bin.is_some() || self.sole_bin;
+        if example_selected && bin_selected {
+            return Err(r#"conflicting usage of --example and --bin.
+Only one target can be run at a time.
+Remove one flag or the other to continue."#
+                .to_owned());
+        }
+        if self.all_examples && (example_selected || bin_selected) {
+            return Err(
+                r#"conflicting usage of --all-examples and --example or --bin.
+The `--all-examples` flag already selects every example.
+Remove one flag or the other to continue."#
+                    .to_owned(),
+            );
+        }
+
+        if self.packages.len() > 1 {
+            if self.all_examples || example_selected || bin_selected {
+                return Err(r#"conflicting usage of multiple --package and --example, --bin or --all-examples.
+Examples and bins can only be selected from a single package.
+Remove the extra packages or the other flags to continue."#
+                    .to_owned());
+            }
+            let metadata = self.workspace_metadata(cargo, workspace_root, workspace)?;
+            let errors: Vec<String> = self
+                .packages
+                .iter()
+                .filter_map(|package| metadata.check_single_bin(package).err())
+                .collect();
+            if !errors.is_empty() {
+                return Err(errors.join("\n"));
+            }
+            return Ok(self
+                .packages
+                .iter()
+                .map(|package| BuildTarget {
+                    name: package.clone(),
+                    kind: TargetKind::Package,
+                })
+                .collect());
+        }
+
+        let pattern = match (&self.example, &self.bin) {
+            (Some(example), _) if is_pattern(example) => Some((example, TargetKind::Example)),
+            (_, Some(bin)) if is_pattern(bin) => Some((bin, TargetKind::Bin)),
+            _ => None,
+        };
+        if let Some((pattern, kind)) = pattern {
+            let metadata = self.workspace_metadata(cargo, workspace_root, workspace)?;
+            let kind_name = kind.cargo_kind();
+            let matched: Vec<&str> = metadata
+                .targets(self.package(), kind_name)
+                .into_iter()
+                .filter(|name| pattern_matches(pattern, name))
+                .collect();
+            if matched.is_empty() {
+                return Err(format!(
+                    "`--{kind_name} {pattern}` did not match any {kind_name} targets"
+                ));
+            }
+            if !self.quiet {
+                println!("`--{kind_name} {pattern}` matched: {}", matched.join(", "));
+            }
+            return Ok(matched
+                .into_iter()
+                .map(|name| BuildTarget {
+                    name: name.to_owned(),
+                    kind,
+                })
+                .collect());
+        }
+
+        if self.all_examples {
+            let metadata = self.workspace_metadata(cargo, workspace_root, workspace)?;
+            let examples = metadata.targets(self.package(), "example");
+            if examples.is_empty() {
+                return Err(
+                    "`--all-examples` was used but there are no example targets available"
+                        .to_owned(),
+                );
+            }
+            return Ok(examples
+                .into_iter()
+                .map(|name| BuildTarget {
+                    name: name.to_owned(),
+                    kind: TargetKind::Example,
+                })
+                .collect());
+        }
+
+        let mut example = self.example.clone();
+        let mut bin = self.bin.clone();
+        if (example.is_none() && self.sole_example) || (bin.is_none() && self.sole_bin) {
+            let metadata = self.workspace_metadata(cargo, workspace_root, workspace)?;
+            if self.sole_example {
+                example = Some(metadata.sole_target(self.package(), "example")?);
+            } else {
+                bin = Some(metadata.sole_target(self.package(), "bin")?);
+            }
+        }
+
+        let target = if let Some(example) = example {
+            BuildTarget {
+                name: example,
+                kind: TargetKind::Example,
+            }
+        } else if let Some(bin) = bin {
+            BuildTarget {
+                name: bin,
+                kind: TargetKind::Bin,
+            }
+        } else if let Some(package) = self.package() {
+            self.workspace_metadata(cargo, workspace_root, workspace)?
+                .check_single_bin(package)?;
+            BuildTarget {
+                name: package.to_owned(),
+                kind: TargetKind::Package,
+            }
+        } else {
+            // A virtual manifest has no package of its own for cargo to fall back to, so point at the members instead
+            let manifest_path = workspace_root.join(self.manifest_path().unwrap_or("Cargo.toml"));
+            if let Ok(metadata) = self.workspace_metadata(cargo, workspace_root, workspace) {
+                if metadata.is_virtual(&manifest_path) {
+                    let members: Vec<&str> =
+                        metadata.packages.iter().map(|x| x.name.as_str()).collect();
+                    return Err(format!(
+                        "{manifest_path:?} is a virtual workspace manifest, so a package must be selected with `--package NAME`. The workspace members are:\n{}",
+                        members.join("\n")
+                    ));
+                }
+            }
+            return Err("Need to use at least one of `--package NAME`, `--example NAME` `--bin NAME`.\nRun cargo run-wasm --help for more info.".to_owned());
+        };
+        Ok(vec![target])
+    }
+
+    /// The target directory that the wasm build uses
+    fn cargo_directories(&self, cargo: &Path) -> Result<CargoDirectories, String> {
+        CargoDirectories::new(
+            cargo,
+            self.package_path.as_ref().map(|x| x.dir.as_path()),
+            &self.cargo_flags(),
+            self.toolchain.as_deref(),
+        )
+    }
+
+    /// Like `cargo_directories`, but only asks cargo once per `workspace`
+    fn workspace_directories(
+        &self,
+        cargo: &Path,
+        workspace: &mut Workspace,
+    ) -> Result<CargoDirectories, String> {
+        if workspace.directories.is_none() {
+            workspace.directories = Some(self.cargo_directories(cargo)?);
+        }
+        Ok(workspace.directories.clone().unwrap())
+    }
+
+    /// Like `cargo_metadata`, but only runs `cargo metadata` once per `workspace`
+    fn workspace_metadata<'a>(
+        &self,
+        cargo: &Path,
+        workspace_root: &Path,
+        workspace: &'a mut Workspace,
+    ) -> Result<&'a CargoMetadata, String> {
+        if workspace.metadata.is_none() {
+            workspace.metadata = Some(self.cargo_metadata(cargo, workspace_root)?);
+        }
+        Ok(workspace.metadata.as_ref().unwrap())
+    }
+
+    fn cargo_metadata(&self, cargo: &Path, workspace_root: &Path) -> Result<CargoMetadata, String> {
+        // Describe the same workspace that the build will use
+        let mut flags: Vec<&str> = self.cargo_flags();
+        if let Some(manifest_path) = self.manifest_path() {
+            flags.extend(["--manifest-path", manifest_path]);
+        }
+        CargoMetadata::new(cargo, workspace_root, &flags, self.toolchain.as_deref())
+    }
+
+    /// The `--manifest-path` set by `with_package_path` or passed through to cargo, if any
+    fn manifest_path(&self) -> Option<&str> {
+        if let Some(package_path) = &self.package_path {
+            return Some(&package_path.manifest_path);
+        }
+        let mut args = self.cargo_build_args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--manifest-path" {
+                return args.next().map(|x| x.as_str());
+            }
+            if let Some(path) = arg.strip_prefix("--manifest-path=") {
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    /// The flags restricting cargo's access to the network and lockfile, passed to every cargo invocation
+    fn cargo_flags(&self) -> Vec<&'static str> {
+        cargo_flags(self.locked, self.offline, self.frozen)
+    }
+
+    fn wasm_target_dir(&self, directories: &CargoDirectories) -> PathBuf {
+        if self.shared_target_dir {
+            directories.target_directory.clone()
+        } else {
+            directories.target_directory.join("wasm-examples-target")
+        }
+    }
+
+    /// The arguments selecting what cargo builds for `target` and how
+    fn cargo_target_args(&self, target: &ResolvedTarget) -> Vec<String> {
+        let mut args = vec![];
+        let package = match target.kind {
+            TargetKind::Package => Some(target.name.as_str()),
+            TargetKind::Example | TargetKind::Bin => self.package(),
+        };
+        if let Some(package_path) = &self.package_path {
+            args.extend([
+                "--manifest-path".to_owned(),
+                package_path.manifest_path.clone(),
+            ]);
+        }
+        if let Some(package) = package {
+            args.extend(["--package".to_owned(), package.to_owned()]);
+        }
+        match target.kind {
+            TargetKind::Example => args.extend(["--example".to_owned(), target.name.clone()]),
+            TargetKind::Bin => args.extend(["--bin".to_owned(), target.name.clone()]),
+            TargetKind::Package => {}
+        }
+        if let Some(profile) = &self.profile {
+            args.extend(["--profile".to_owned(), profile.clone()]);
+        }
+        args.extend(self.cargo_flags().into_iter().map(|flag| flag.to_owned()));
+        if self.keep_going {
+            args.push("--keep-going".to_owned());
+        }
+        if let Some(jobs) = self.jobs {
+            args.extend(["--jobs".to_owned(), jobs.to_string()]);
+        }
+        if !self.features.is_empty() {
+            args.extend(["--features".to_owned(), self.features.join(",")]);
+        }
+        args
+    }
+
+    /// Build the target with cargo, returning the wasm file cargo produced
+    fn cargo_build(
+        &self,
+        cargo: &Path,
+        directories: &CargoDirectories,
+        target: &ResolvedTarget,
+    ) -> Result<PathBuf, RunWasmError> {
+        let workspace_root = &directories.workspace_root;
+        let target_target = self.wasm_target_dir(directories);
+        let mut cargo_args = vec![
+            "build".as_ref(),
+            "--target".as_ref(),
+            "wasm32-unknown-unknown".as_ref(),
+            // It is common to setup a faster linker such as mold or lld to run for just your native target.
+            // It cant be set for wasm as wasm doesnt support building with these linkers.
+            // This results in a separate rustflags value for native and wasm builds.
+            // Currently rust triggers a full rebuild every time the rustflags value changes.
+            //
+            // Therefore we have this hack where we use a different target dir for wasm builds to avoid constantly triggering full rebuilds.
+            // When this issue is resolved we might be able to remove this hack: https://github.com/rust-lang/cargo/issues/8716
+            // In the meantime users who dont hit this problem can opt out of the hack via `with_shared_target_dir`.
+            "--target-dir".as_ref(),
+            target_target.as_os_str(),
+        ];
+
+        let target_args = self.cargo_target_args(target);
+        cargo_args.extend(target_args.iter().map(OsStr::new));
+        let rustflags = self.rustflags();
+        if let Some(Rustflags::Config(config)) = &rustflags {
+            // Arrays from `--config` are merged with those of the config files, so the project's own rustflags are kept
+            cargo_args.extend([OsStr::new("--config"), config.as_ref()]);
+        }
+        let build_std = format!("build-std={}", self.build_std.join(","));
+        if !self.build_std.is_empty() {
+            if !is_nightly(workspace_root, self.toolchain.as_deref()) {
+                return Err(RunWasmError::InvalidConfig {
+                    reason: "`with_build_std` requires a nightly toolchain.\nTry adding a rust-toolchain.toml or using `cargo +nightly`.".to_owned(),
+                });
+            }
+            cargo_args.extend([OsStr::new("-Z"), build_std.as_ref()]);
+        }
+
+        if self.quiet {
+            cargo_args.push("--quiet".as_ref());
+        }
+        for _ in 0..self.verbose {
+            cargo_args.push("--verbose".as_ref());
+        }
+        cargo_args.extend(self.cargo_build_args.iter().map(OsStr::new));
+        // Have cargo tell us exactly which files it produced instead of reconstructing the path from naming conventions.
+        // The render-diagnostics variant keeps warnings and errors printed to stderr in the usual human readable format.
+        cargo_args.push("--message-format=json-render-diagnostics".as_ref());
+
+        let command_line = format_command(cargo, &cargo_args);
+        if self.verbose > 0 {
+            println!("Running `{command_line}` in {workspace_root:?}");
+        }
+        let mut command = Command::new(cargo);
+        command
+            .current_dir(workspace_root)
+            .args(&cargo_args)
+            .stdout(Stdio::piped());
+        if let Some(toolchain) = &self.toolchain {
+            command.env("RUSTUP_TOOLCHAIN", toolchain);
+        }
+        match &rustflags {
+            Some(Rustflags::Env(rustflags)) => {
+                // CARGO_ENCODED_RUSTFLAGS takes priority over RUSTFLAGS so remove it to ensure our flags are used.
+                command
+                    .env("RUSTFLAGS", rustflags)
+                    .env_remove("CARGO_ENCODED_RUSTFLAGS");
+            }
+            Some(Rustflags::Encoded(rustflags)) => {
+                command.env("CARGO_ENCODED_RUSTFLAGS", rustflags);
+            }
+            Some(Rustflags::Config(_)) | None => {}
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|err| format!("Failed to run `{command_line}`: {err}"))?;
+
+        let target_kind = target.kind.cargo_kind();
+        let mut wasm_source = None;
+        for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+            let line = line
+                .map_err(|err| format!("Failed to read the output of `{command_line}`: {err}"))?;
+            match serde_json::from_str::<serde_json::Value>(&line) {
+                Ok(message) => {
+                    if let Some(path) = wasm_artifact(&message, &target.name, target_kind) {
+                        wasm_source = Some(path);
+                    }
+                }
+                // Not a cargo message, so pass it through untouched
+                Err(_) => println!("{line}"),
+            }
+        }
+
+        let status = child
+            .wait()
+            .map_err(|err| format!("Failed to wait for `{command_line}`: {err}"))?;
+        if !status.success() {
+            // We can return without printing anything because cargo will have already displayed an appropriate error.
+            return Err(RunWasmError::CargoBuildFailed);
+        }
+
+        wasm_source.ok_or_else(|| RunWasmError::ArtifactNotFound {
+            kind: target_kind,
+            name: target.name.clone(),
+        })
+    }
+
+    /// Run wasm-bindgen on the wasm file and generate an index.html, writing everything into `example_dest`
+    fn generate(
+        &self,
+        binary_name: &str,
+        wasm_source: &Path,
+        example_dest: &Path,
+        build_id: &str,
+    ) -> Result<TargetOutput, RunWasmError> {
+        if self.clean_output && example_dest.exists() {
+            std::fs::remove_dir_all(example_dest).map_err(|err| {
+                format!("Failed to clean the output directory {example_dest:?}: {err}")
+            })?;
+        }
+        std::fs::create_dir_all(example_dest).map_err(|err| {
+            format!("Failed to create the output directory {example_dest:?}: {err}")
+        })?;
+        // wasm-bindgen names its output after the input file rather than the target.
+        // These can differ as cargo replaces hyphens in target names with underscores when naming artifacts.
+        let module_name = wasm_source
+            .file_stem()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_owned())
+            .unwrap_or_else(|| binary_name.replace('-', "_"));
+        match &self.external_bindgen {
+            Some(wasm_bindgen) => {
+                self.run_external_bindgen(wasm_bindgen, wasm_source, example_dest)?
+            }
+            None => self.run_bindgen(wasm_source, example_dest)?,
+        }
+        if self.minify_js {
+            let js = example_dest.join(format!("{module_name}.js"));
+            let source = std::fs::read_to_string(&js)
+                .map_err(|err| format!("Failed to read the wasm-bindgen output {js:?}: {err}"))?;
+            let minified = minify::minify_js(&source);
+            let mut code = minified.code;
+            // An inlined script is loaded from a data url, which the map could not be resolved relative to
+            if self.source_maps && !self.inline_wasm {
+                let map = example_dest.join(format!("{module_name}.js.map"));
+                let contents = source_map::generate(
+                    &format!("{module_name}.js"),
+                    &format!("{module_name}.unminified.js"),
+                    &source,
+                    &minified.lines,
+                );
+                std::fs::write(&map, contents)
+                    .map_err(|err| format!("Failed to write the source map {map:?}: {err}"))?;
+                if !code.ends_with('\n') {
+                    code.push('\n');
+                }
+                code.push_str(&format!("//# sourceMappingURL={module_name}.js.map\n"));
+            }
+            std::fs::write(&js, code)
+                .map_err(|err| format!("Failed to write the minified js to {js:?}: {err}"))?;
+        }
+        let wasm = example_dest.join(format!("{module_name}_bg.wasm"));
+        if !self.snip.is_empty() {
+            self.run_wasm_snip(&wasm)?;
+        }
+        if self.validate_imports {
+            validate_imports(&wasm)?;
+        }
+        self.check_wasm_size(&wasm);
+
+        self.render_page(binary_name, &module_name, example_dest, build_id)
+            .map_err(RunWasmError::from)
+    }
+
+    /// Suggest a release build if a debug build produced a wasm file large enough to noticeably slow down loading the page
+    fn check_wasm_size(&self, wasm: &Path) {
+        let threshold = match self.size_warning_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        if self.quiet {
+            return;
+        }
+        let debug = matches!(self.profile.as_deref(), None | Some("dev"));
+        let size = std::fs::metadata(wasm).map(|x| x.len()).unwrap_or(0);
+        if debug && size > threshold {
+            println!(
+                "{} {wasm:?} is {:.1}MB, which can make the page slow to load. Building with --release and running wasm-opt makes it much smaller.",
+                color::hint("hint:"),
+                size as f64 / 1_000_000.0
+            );
+        }
+    }
+
+    /// The files and directories to bundle, along with their paths within the bundle
+    fn bundle_sources(&self, output: &BuildOutput) -> Result<Vec<(PathBuf, String)>, String> {
+        // Static dirs come first as they take precedence when served
+        let mut sources: Vec<(PathBuf, String)> = self
+            .static_dirs
+            .iter()
+            .map(|dir| (dir.clone(), String::new()))
+            .collect();
+        if output.targets.len() == 1 {
+            sources.push((output.dir.clone(), String::new()));
+            return Ok(sources);
+        }
+
+        // The shared directory also contains the targets built by earlier runs, so only take the files at its root,
+        // such as the page linking to each target, and the directories of the targets built now.
+        let mut entries = std::fs::read_dir(&output.dir)
+            .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| format!("Failed to read {:?}: {err}", output.dir))?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            if path.is_file() {
+                sources.push((path, entry.file_name().to_string_lossy().into_owned()));
+            }
+        }
+        for target in &output.targets {
+            let name = target
+                .dir
+                .strip_prefix(&output.dir)
+                .unwrap_or(&target.dir)
+                .to_string_lossy()
+                .replace('\\', "/");
+            sources.push((target.dir.clone(), name));
+        }
+        Ok(sources)
+    }
+
+    /// Generate the js glue for `wasm_source` into `example_dest` with the wasm-bindgen library run-wasm was built with
+    fn run_bindgen(&self, wasm_source: &Path, example_dest: &Path) -> Result<(), RunWasmError> {
+        let mut bindgen = wasm_bindgen_cli_support::Bindgen::new();
+        match self.runtime {
+            Runtime::Browser => bindgen.web(true),
+            Runtime::Node => bindgen.nodejs(true),
+            Runtime::Deno => bindgen.deno(true),
+        }
+        .map_err(|err| RunWasmError::BindgenFailed {
+            reason: format!("Failed to configure wasm-bindgen: {err:#}"),
+        })?;
+        if let Some(encode_into) = self.encode_into {
+            bindgen.encode_into(match encode_into {
+                EncodeInto::Test => wasm_bindgen_cli_support::EncodeInto::Test,
+                EncodeInto::Always => wasm_bindgen_cli_support::EncodeInto::Always,
+                EncodeInto::Never => wasm_bindgen_cli_support::EncodeInto::Never,
+            });
+        }
+        bindgen
+            .demangle(self.demangle)
+            .split_linked_modules(self.split_linked_modules)
+            .omit_default_module_path(self.omit_default_module_path)
+            .input_path(wasm_source)
+            .generate(example_dest)
+            // The alternate format includes the underlying cause, which is often the only useful part e.g. a schema version mismatch
+            .map_err(|err| RunWasmError::BindgenFailed {
+                reason: format!(
+                    "wasm-bindgen failed to generate bindings for {wasm_source:?} into {example_dest:?}: {err:#}"
+                ),
+            })
+    }
+
+    /// Generate the js glue for `wasm_source` into `example_dest` by running an external wasm-bindgen CLI
+    fn run_external_bindgen(
+        &self,
+        wasm_bindgen: &Path,
+        wasm_source: &Path,
+        example_dest: &Path,
+    ) -> Result<(), RunWasmError> {
+        let target = match self.runtime {
+            Runtime::Browser => "web",
+            Runtime::Node => "nodejs",
+            Runtime::Deno => "deno",
+        };
+        let mut command = Command::new(wasm_bindgen);
+        command
+            .args(["--target", target, "--out-dir"])
+            .arg(example_dest)
+            .arg(wasm_source);
+        if !self.demangle {
+            command.arg("--no-demangle");
+        }
+        if self.split_linked_modules {
+            command.arg("--split-linked-modules");
+        }
+        if self.omit_default_module_path {
+            command.arg("--omit-default-module-path");
+        }
+        if let Some(encode_into) = self.encode_into {
+            let mode = match encode_into {
+                EncodeInto::Test => "test",
+                EncodeInto::Always => "always",
+                EncodeInto::Never => "never",
+            };
+            command.args(["--encode-into", mode]);
+        }
+
+        let status = command
+            .status()
+            .map_err(|err| RunWasmError::BindgenFailed {
+                reason: format!("Failed to run wasm-bindgen at {wasm_bindgen:?}: {err}"),
+            })?;
+        if !status.success() {
+            // wasm-bindgen will have already printed the reason
+            return Err(RunWasmError::BindgenFailed {
+                reason: format!(
+                    "wasm-bindgen at {wasm_bindgen:?} failed to generate bindings for {wasm_source:?}"
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Snip the functions matching `self.snip` out of `wasm` in place
+    fn run_wasm_snip(&self, wasm: &Path) -> Result<(), String> {
+        let wasm_snip = Path::new("wasm-snip");
+        let mut command = Command::new(wasm_snip);
+        command.arg(wasm).arg("--output").arg(wasm);
+        for pattern in &self.snip {
+            command.args(["--pattern", pattern.as_str()]);
+        }
+        if self.verbose > 0 {
+            let args: Vec<&OsStr> = command.get_args().collect();
+            println!("Running `{}`", format_command(wasm_snip, &args));
+        }
+        match command.status() {
+            Ok(status) if status.success() => Ok(()),
+            // wasm-snip will have already printed the reason
+            Ok(status) => Err(format!("wasm-snip failed to snip {wasm:?} with {status}")),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                println!(
+                    "{} wasm-snip is not installed so no functions were snipped, install it with `cargo install wasm-snip`",
+                    color::warning("warning:")
+                );
+                Ok(())
+            }
+            Err(err) => Err(format!("Failed to run wasm-snip: {err}")),
+        }
+    }
+
+    /// With `with_html_only`, skip building and only regenerate the index.html if `example_dest` contains the output of a previous build.
+    /// Returns None if a full build is needed.
+    fn reuse_previous_build(
+        &self,
+        target: &ResolvedTarget,
+        build_id: &str,
+    ) -> Option<Result<TargetOutput, String>> {
+        if !self.html_only {
+            return None;
+        }
+        // wasm-bindgen names its output after the wasm file
+        let module_name = target.wasm_source.file_stem()?.to_str()?;
+        let js = target.dest.join(format!("{}.js", module_name));
+        let wasm = target.dest.join(format!("{}_bg.wasm", module_name));
+        if !js.is_file() || !wasm.is_file() {
+            if !self.quiet {
+                println!(
+                    "No previous build of `{}` found, falling back to a full build",
+                    target.name
+                );
+            }
+            return None;
+        }
+        Some(self.render_page(&target.name, module_name, &target.dest, build_id))
+    }
+
+    /// Generate the index.html for the wasm-bindgen output in `example_dest`
+    fn render_page(
+        &self,
+        binary_name: &str,
+        module_name: &str,
+        example_dest: &Path,
+        build_id: &str,
+    ) -> Result<TargetOutput, String> {
+        let wasm = example_dest.join(format!("{}_bg.wasm", module_name));
+        let js = example_dest.join(format!("{}.js", module_name));
+        let html = example_dest.join(&self.index_file);
+        // Node and Deno execute the js glue directly so there is no page to generate
+        if self.runtime == Runtime::Browser {
+            // process template index.html and write to the destination folder
+            let mut meta = vec![format!(
+                r#"<meta name="run-wasm-build-id" content="{}" />"#,
+                template::escape_attribute(build_id)
+            )];
+            if self.viewport_meta {
+                meta.push(
+                    r#"<meta name="viewport" content="width=device-width, initial-scale=1.0" />"#
+                        .to_owned(),
+                );
+            }
+            for (name, content) in &self.meta {
+                meta.push(format!(
+                    r#"<meta name="{}" content="{}" />"#,
+                    template::escape_attribute(name),
+                    template::escape_attribute(content)
+                ));
+            }
+            if self.preload_wasm && !self.inline_wasm {
+                // crossorigin matches the mode wasm-bindgen fetches the wasm with, otherwise the preload is not reused
+                meta.push(format!(
+                    r#"<link rel="preload" as="fetch" crossorigin href="./{}_bg.wasm" />"#,
+                    template::escape_attribute(module_name)
+                ));
+            }
+            if let Some(import_map) = &self.import_map {
+                // Import maps must come before any module script, `<` is escaped so the json can never close the script element
+                meta.push(format!(
+                    r#"<script type="importmap">{}</script>"#,
+                    import_map.to_string().replace('<', "\\u003c")
+                ));
+            }
+            if self.live_reload {
+                meta.push(template::render(
+                    LIVE_RELOAD_SCRIPT,
+                    &[("path", server::LIVE_RELOAD_PATH)],
+                ));
+            }
+            if let Some(log_level) = &self.log_level {
+                // Non module scripts run first, so the global is set before the wasm starts
+                meta.push(format!(
+                    "<script>window.RUN_WASM_LOG_LEVEL = {};</script>",
+                    template::escape_js_string(log_level)
+                ));
+            }
+            let (script_src, init_args) = if self.inline_wasm {
+                inline_wasm(&js, &wasm)?
+            } else if self.omit_default_module_path {
+                // The glue no longer knows where the wasm file is, so it is passed in relative to the page instead
+                (
+                    format!("./{module_name}.js"),
+                    template::escape_js_string(&format!("./{module_name}_bg.wasm")),
+                )
+            } else {
+                (format!("./{module_name}.js"), String::new())
+            };
+            let (css, body) = if self.fullscreen_canvas {
+                (
+                    format!("{FULLSCREEN_CANVAS_CSS}\n{}", self.css),
+                    FULLSCREEN_CANVAS_BODY,
+                )
+            } else {
+                (self.css.clone(), "")
+            };
+            let index_processed = template::Index {
+                name: &template::escape_attribute(binary_name),
+                lang: &template::escape_attribute(&self.lang),
+                dir: self.dir.as_str(),
+                script_src: &script_src,
+                init_args: &init_args,
+                css: &css,
+                body,
+                meta: &meta.join("\n  "),
+                head: self.head.trim_end(),
+                init: &self.init_script,
+            }
+            .render();
+            std::fs::write(&html, index_processed)
+                .map_err(|err| format!("Failed to write {html:?}: {err}"))?;
+        }
+
+        for (path, contents) in &self.files {
+            let file = example_dest.join(relative_file_path(path).ok_or_else(|| {
+                format!(
+                    "The file path `{path}` must be relative and stay within the output directory"
+                )
+            })?);
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|err| format!("Failed to create {parent:?}: {err}"))?;
+            }
+            std::fs::write(&file, contents)
+                .map_err(|err| format!("Failed to write {file:?}: {err}"))?;
+        }
+
+        Ok(TargetOutput {
+            wasm,
+            js,
+            html,
+            dir: example_dest.to_owned(),
+            name: binary_name.to_owned(),
+        })
+    }
+
+    /// Launch run-wasm
+    ///
+    /// Once the dev server is listening it responds to `/__run_wasm_health` with json containing the build id and the names of the built targets,
+    /// which automation can poll to know when the page is ready to load.
+    pub fn run(mut self) -> Result<(), RunWasmError> {
+        let mut workspace = Workspace::default();
+        let resolved = self.validate_in(&mut workspace)?;
+        let output = self.build_resolved(resolved, &mut workspace)?;
+
+        if let Some(post_build) = self.post_build.take() {
+            post_build(&output.dir)?;
+        }
+
+        if self.echo_html && self.runtime == Runtime::Browser {
+            for target in &output.targets {
+                let html = std::fs::read_to_string(&target.html)
+                    .map_err(|err| format!("Failed to read {:?}: {err}", target.html))?;
+                println!("{:?}:\n{html}", target.html);
+            }
+        }
+
+        if let Some(bundle) = &self.bundle {
+            bundle::write_zip(&self.bundle_sources(&output)?, bundle)?;
+            if !self.quiet {
+                println!("Bundled the generated files into {bundle:?}");
+            }
+        }
+
+        if self.print_paths {
+            output.print_paths();
+        }
+
+        if self.build_only {
+            return Ok(());
+        }
+        if self.runtime != Runtime::Browser {
+            for target in &output.targets {
+                self.runtime.execute(&target.js)?;
+            }
+            return Ok(());
+        }
+
+        check_index(&output.dir.join(&self.index_file))?;
+
+        // Static dirs take precedence over the generated files, so a conflict would silently break the app
+        let generated = std::iter::once(&output.html).chain(
+            output
+                .targets
+                .iter()
+                .flat_map(|target| [&target.html, &target.js, &target.wasm]),
+        );
+        for file in generated {
+            let relative = file.strip_prefix(&output.dir).unwrap_or(file);
+            for static_dir in &self.static_dirs {
+                if static_dir.join(relative).exists() {
+                    return Err(RunWasmError::InvalidConfig {
+                        reason: format!(
+                            "The static directory {static_dir:?} contains {relative:?} which conflicts with the generated file of the same name"
+                        ),
+                    });
+                }
+            }
+        }
+
+        let host = self.host.unwrap_or_else(|| "localhost".into());
+        let port = self.port.unwrap_or_else(|| "8000".into());
+        let port: u16 = port
+            .parse()
+            .map_err(|_| format!("The port `{port}` is not a valid port number"))?;
+        let bind_error = |err: std::io::Error| {
+            let reason = match err.kind() {
+                std::io::ErrorKind::AddrInUse => {
+                    "the address is already in use, use --port to pick a different port".to_owned()
+                }
+                std::io::ErrorKind::AddrNotAvailable => {
+                    "the address is not available on this machine".to_owned()
+                }
+                std::io::ErrorKind::PermissionDenied => {
+                    "permission denied, ports below 1024 usually require elevated privileges"
+                        .to_owned()
+                }
+                _ => err.to_string(),
+            };
+            RunWasmError::ServerBindFailed {
+                addr: format!("{host}:{port}"),
+                reason,
+            }
+        };
+
+        // run webserver on destination folder
+        let (listener, url) = match (self.listener.take(), &self.unix_socket, self.address_family) {
+            (Some(listener), _, _) => {
+                let address = listener
+                    .local_addr()
+                    .map_err(|err| format!("Could not get the address of the listener: {err}"))?;
+                let host = if address.ip().is_unspecified() {
+                    "localhost".to_owned()
+                } else {
+                    address.ip().to_string()
+                };
+                (
+                    server::Listener::Tcp(listener),
+                    server_url(&host, address.port(), &self.base_path),
+                )
+            }
+            // Clients connecting over the socket still need a url for the request, the host is ignored
+            (None, Some(unix_socket), _) => (
+                bind_unix_socket(unix_socket)?,
+                "http://localhost/".to_owned(),
+            ),
+            (None, None, None) => (
+                server::Listener::Tcp(
+                    TcpListener::bind((host.as_str(), port)).map_err(bind_error)?,
+                ),
+                server_url(&host, port, &self.base_path),
+            ),
+            // Resolve the host ourselves so that only addresses of the requested family are considered.
+            // The url then uses the ip address, as the host name may resolve to a different family in the browser.
+            (None, None, Some(family)) => {
+                let address = (host.as_str(), port)
+                    .to_socket_addrs()
+                    .map_err(|err| format!("Could not resolve the host `{host}`: {err}"))?
+                    .find(|address| match family {
+                        AddressFamily::Ipv4 => address.is_ipv4(),
+                        AddressFamily::Ipv6 => address.is_ipv6(),
+                    })
+                    .ok_or_else(|| {
+                        format!("The host `{host}` does not resolve to an {family:?} address")
+                    })?;
+                (
+                    server::Listener::Tcp(TcpListener::bind(address).map_err(bind_error)?),
+                    server_url(&address.ip().to_string(), port, &self.base_path),
+                )
+            }
+        };
+        let names: Vec<String> = output
+            .targets
+            .iter()
+            .map(|target| format!("`{}`", target.name))
+            .collect();
+        if !self.quiet {
+            match &self.unix_socket {
+                Some(unix_socket) => println!(
+                    "\n{} {} on the unix socket {unix_socket:?}",
+                    color::status("Serving"),
+                    names.join(", ")
+                ),
+                None => println!(
+                    "\n{} {} on {url}",
+                    color::status("Serving"),
+                    names.join(", ")
+                ),
+            }
+            // Listening on every interface, so the url may not be one this machine can be reached at
+            if let server::Listener::Tcp(listener) = &listener {
+                if let Ok(address) = listener.local_addr() {
+                    if address.ip().is_unspecified() {
+                        println!(
+                            "Locally available on {}",
+                            server_url("127.0.0.1", address.port(), &self.base_path)
+                        );
+                    }
+                }
+            }
+        }
+
+        // A browser cannot reach the page over a unix socket
+        if (self.open || self.force_open) && self.unix_socket.is_none() {
+            if is_ci() && !self.force_open {
+                if !self.quiet {
+                    println!("Not opening the browser as a CI environment was detected, use --force-open to open it anyway");
+                }
+            } else if let Err(err) = open_browser(&url) {
+                // The page can still be opened by hand, so this is not worth failing over
+                println!(
+                    "{} Failed to open the browser: {err}",
+                    color::warning("warning:")
+                );
+            }
+        }
+
+        let mut on_ready = match self.on_ready_command.split_first() {
+            Some((program, args)) => Some({
+                let mut command = Command::new(program);
+                command.args(args).env("RUN_WASM_URL", &url);
+                if let Some(unix_socket) = &self.unix_socket {
+                    command.env("RUN_WASM_UNIX_SOCKET", unix_socket);
+                }
+                command.spawn().map_err(|err| {
+                    format!("Failed to launch the on ready command `{program}`: {err}")
+                })?
+            }),
+            None => None,
+        };
+        let mut on_ready_status = None;
+        let mut on_ready_exited = || {
+            if let Some(child) = &mut on_ready {
+                on_ready_status = child.try_wait().unwrap_or(None);
+            }
+            on_ready_status.is_some()
+        };
+        let stop = if self.on_ready_command.is_empty() {
+            None
+        } else {
+            Some(&mut on_ready_exited as &mut dyn FnMut() -> bool)
+        };
+
+        let health = serde_json::json!({
+            "build_id": output.build_id,
+            "targets": output.targets.iter().map(|x| &x.name).collect::<Vec<_>>(),
+        })
+        .to_string();
+        server::Server {
+            dir: output.dir,
+            cache_policy: self.cache_policy,
+            spa_fallback: self.spa_fallback,
+            index_file: self.index_file,
+            base_path: self.base_path,
+            proxies: self.proxies,
+            access_log: self.access_log && !self.quiet,
+            routes: self.routes,
+            static_dirs: self.static_dirs,
+            allowed_hosts: self.allowed_hosts,
+            health,
+            mime_types: self.mime_types,
+            live_reload: self.live_reload.then(server::LiveReload::default),
+        }
+        .run(listener, self.serve_timeout, stop)
+        .map_err(|err| format!("Dev server failed: {err}"))?;
+
+        if let Some(mut child) = on_ready {
+            match on_ready_status {
+                Some(status) if !status.success() => {
+                    return Err(format!("The on ready command failed with {status}").into());
+                }
+                Some(_) => {}
+                // The serve timeout elapsed before the command finished
+                None => {
+                    child.kill().ok();
+                    child.wait().ok();
+                    return Err(
+                        "The serve timeout elapsed before the on ready command finished"
+                            .to_owned()
+                            .into(),
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The url of the page served below `base_path` by the dev server.
+/// Includes the trailing slash so that terminals linkify the entire url.
+fn server_url(host: &str, port: u16, base_path: &str) -> String {
+    if host.contains(':') {
+        format!("http://[{host}]:{port}{base_path}")
+    } else {
+        format!("http://{host}:{port}{base_path}")
+    }
+}
+
+#[cfg(unix)]
+fn bind_unix_socket(path: &Path) -> Result<server::Listener, RunWasmError> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // The socket file of a previous run is left behind and would make binding fail
+    if std::fs::symlink_metadata(path).is_ok_and(|x| x.file_type().is_socket()) {
+        std::fs::remove_file(path)
+            .map_err(|err| format!("Failed to remove the old unix socket {path:?}: {err}"))?;
+    }
+    std::os::unix::net::UnixListener::bind(path)
+        .map(server::Listener::Unix)
+        .map_err(|err| RunWasmError::ServerBindFailed {
+            addr: format!("the unix socket {path:?}"),
+            reason: err.to_string(),
+        })
+}
+
+#[cfg(not(unix))]
+fn bind_unix_socket(_path: &Path) -> Result<server::Listener, RunWasmError> {
+    Err("Unix sockets are only supported on unix platforms"
+        .to_owned()
+        .into())
+}
+
+/// Returns true if run-wasm appears to be running in CI, where there is no browser to open
+fn is_ci() -> bool {
+    ["CI", "GITHUB_ACTIONS"]
+        .iter()
+        .any(|var| env::var(var).is_ok_and(|x| !x.is_empty() && x != "false" && x != "0"))
+}
+
+/// Open `url` in the default browser of the platform
+fn open_browser(url: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        // The empty argument is the window title, otherwise start would take a quoted url as the title
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    // The opener may print its own diagnostics, which would be mixed in with the dev server output
+    let mut child = command
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Some openers only exit once the browser does, so reap it in the background rather than blocking the dev server
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// The `host:port` address of the proxy target url `target`
+fn proxy_address(target: &str) -> Result<&str, String> {
+    let address = match target.strip_prefix("http://") {
+        Some(address) => address.trim_end_matches('/'),
+        None => {
+            return Err(format!(
+                "The proxy target `{target}` must be a url starting with `http://`"
+            ))
+        }
+    };
+    if address.contains('/') {
+        return Err(format!(
+            "The proxy target `{target}` must not contain a path"
+        ));
+    }
+    Ok(address)
+}
+
+const FULLSCREEN_CANVAS_CSS: &str =
+    "html, body { margin: 0px; height: 100%; overflow: hidden; } #run-wasm-canvas { display: block; }";
+
+const FULLSCREEN_CANVAS_BODY: &str = r#"<canvas id="run-wasm-canvas"></canvas>
+  <script>
+    // Scoped so that the names do not collide with globals defined by the page
+    (() => {
+      const canvas = document.getElementById("run-wasm-canvas");
+      const resize = () => {
+        canvas.width = window.innerWidth;
+        canvas.height = window.innerHeight;
+      };
+      window.addEventListener("resize", resize);
+      resize();
+    })();
+  </script>"#;
+
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+    // Reload when the dev server reports a change, or when it comes back after being restarted
+    (() => {
+      let connected = false;
+      const connect = () => {
+        const socket = new WebSocket(location.origin.replace(/^http/, "ws") + "{{path}}");
+        socket.onopen = () => {
+          if (connected) {
+            location.reload();
+          }
+          connected = true;
+        };
+        socket.onmessage = (event) => {
+          if (event.data === "reload") {
+            location.reload();
+          }
+        };
+        socket.onclose = () => setTimeout(connect, 1000);
+      };
+      connect();
+    })();
+  </script>"#;
+
+/// The `--locked`, `--offline` and `--frozen` flags to pass to every cargo invocation
+fn cargo_flags(locked: bool, offline: bool, frozen: bool) -> Vec<&'static str> {
+    let mut flags = vec![];
+    if locked {
+        flags.push("--locked");
+    }
+    if offline {
+        flags.push("--offline");
+    }
+    if frozen {
+        flags.push("--frozen");
+    }
+    flags
+}
+
+/// The cargo executable that launched us, otherwise `cargo` from the `PATH`
+fn default_cargo_executable() -> PathBuf {
+    env::var_os("CARGO")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("cargo"))
+}
+
+/// Generate an id that is unique to each build
+fn new_build_id() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{:x}", since_epoch.as_nanos())
+}
+
+/// Embed the js glue and wasm into the page, returning the script src to import the glue from and the arguments to initialize it with
+fn inline_wasm(js: &Path, wasm: &Path) -> Result<(String, String), String> {
+    let js_source = std::fs::read_to_string(js)
+        .map_err(|err| format!("Failed to read the wasm-bindgen output {js:?}: {err}"))?;
+    // Snippets are imported by relative path, which can not be resolved from within a data url
+    if js_source.contains("./snippets/") {
+        return Err(
+            "with_inline_wasm does not support crates that use wasm-bindgen js snippets".to_owned(),
+        );
+    }
+    let wasm_bytes = std::fs::read(wasm)
+        .map_err(|err| format!("Failed to read the wasm-bindgen output {wasm:?}: {err}"))?;
+
+    let script_src = format!(
+        "data:text/javascript;base64,{}",
+        template::base64(js_source.as_bytes())
+    );
+    // The glue instantiates the wasm from the given bytes instead of fetching the wasm file
+    let init_args = format!(
+        r#"Uint8Array.from(atob("{}"), (c) => c.charCodeAt(0))"#,
+        template::base64(&wasm_bytes)
+    );
+    Ok((script_src, init_args))
+}
+
+/// A package selected by the directory containing its Cargo.toml
+struct PackagePath {
+    /// The absolute directory, as cargo runs from the workspace root rather than where the path was given
+    dir: PathBuf,
+    /// The Cargo.toml in `dir`
+    manifest_path: String,
+    name: String,
+}
+
+impl PackagePath {
+    fn new(dir: &Path) -> Result<Self, String> {
+        let dir = std::env::current_dir()
+            .map_err(|err| format!("Could not access the current directory: {err}"))?
+            .join(dir);
+        let name = package_name(&dir)?;
+        let manifest_path = dir
+            .join("Cargo.toml")
+            .into_os_string()
+            .into_string()
+            .map_err(|path| format!("The path {path:?} is not valid utf-8"))?;
+        Ok(PackagePath {
+            dir,
+            manifest_path,
+            name,
+        })
+    }
+}
+
+/// The name of the package with its Cargo.toml in `dir`
+fn package_name(dir: &Path) -> Result<String, String> {
+    let cargo_toml = dir.join("Cargo.toml");
+    let contents = std::fs::read_to_string(&cargo_toml).map_err(|err| {
+        format!("No package found at {dir:?}, could not read {cargo_toml:?}: {err}")
+    })?;
+    let manifest: toml::value::Table = toml::from_str(&contents)
+        .map_err(|err| format!("Failed to parse {cargo_toml:?}: {err}"))?;
+    manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .map(|name| name.to_owned())
+        .ok_or_else(|| {
+            format!("No package found at {dir:?}, {cargo_toml:?} does not have a `[package]` with a name")
+        })
+}
+
+/// Convert the `/` separated `path` of a `with_file` into a relative path, or None if it could escape the output directory
+fn relative_file_path(path: &str) -> Option<PathBuf> {
+    let mut result = PathBuf::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            segment if segment.contains(&['\\', ':'][..]) => return None,
+            segment => result.push(segment),
+        }
+    }
+    if result.as_os_str().is_empty() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+/// Check that the wasm-bindgen output `wasm` only imports from the js glue
+fn validate_imports(wasm: &Path) -> Result<(), String> {
+    let bytes = std::fs::read(wasm).map_err(|err| format!("Failed to read {wasm:?}: {err}"))?;
+    let imports =
+        wasm::imports(&bytes).map_err(|err| format!("Failed to parse {wasm:?}: {err}"))?;
+    // The glue provides its imports under `wbg` for the web target and `__wbindgen_placeholder__` for node and deno
+    let unexpected: Vec<String> = imports
+        .iter()
+        .filter(|(module, _)| {
+            module != "wbg"
+                && module != "__wbindgen_placeholder__"
+                && !module.starts_with("./snippets/")
+        })
+        .map(|(module, field)| format!("  {module}.{field}"))
+        .collect();
+    if unexpected.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{wasm:?} imports functions that are not provided by wasm-bindgen and will fail to load:\n{}",
+            unexpected.join("\n")
+        ))
+    }
+}
+
+/// Check that `path` is a wasm module, returning the name to serve it under
+fn validate_prebuilt_wasm(path: &Path) -> Result<String, String> {
+    let mut magic = [0; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map_err(|err| format!("Could not read the prebuilt wasm file {path:?}: {err}"))?;
+    if &magic != b"\0asm" {
+        return Err(format!(
+            "The prebuilt wasm file {path:?} is not a wasm module"
+        ));
+    }
+
+    path.file_stem()
+        .and_then(|x| x.to_str())
+        .map(|x| x.to_owned())
+        .ok_or_else(|| {
+            format!("The prebuilt wasm file {path:?} does not have a valid utf-8 file name")
+        })
+}
+
+/// Check that `profile` is either built into cargo or defined by the workspace Cargo.toml, a cargo config file or a `CARGO_PROFILE_*` env var.
+/// Otherwise cargo would only report the typo after a delay.
+fn validate_profile(profile: &str, workspace_root: &Path) -> Result<(), String> {
+    let mut profiles: Vec<String> = ["dev", "release", "test", "bench"]
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+
+    let mut files = vec![workspace_root.join("Cargo.toml")];
+    // cargo merges the config files of every parent directory and the cargo home
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")));
+    let config_dirs = workspace_root
+        .ancestors()
+        .map(|dir| dir.join(".cargo"))
+        .chain(cargo_home);
+    for dir in config_dirs {
+        files.push(dir.join("config.toml"));
+        files.push(dir.join("config"));
+    }
+    for file in files {
+        let table = match std::fs::read_to_string(&file)
+            .map(|x| toml::from_str::<toml::value::Table>(&x))
+        {
+            Ok(Ok(table)) => table,
+            // Files that are missing or invalid are reported by cargo itself
+            _ => continue,
+        };
+        if let Some(defined) = table.get("profile").and_then(|x| x.as_table()) {
+            profiles.extend(defined.keys().cloned());
+        }
+    }
+    if profiles.iter().any(|x| x == profile) {
+        return Ok(());
+    }
+
+    let env_prefix = format!(
+        "CARGO_PROFILE_{}_",
+        profile.to_ascii_uppercase().replace('-', "_")
+    );
+    if env::vars_os().any(|(key, _)| key.to_string_lossy().starts_with(&env_prefix)) {
+        return Ok(());
+    }
+
+    profiles.sort();
+    profiles.dedup();
+    Err(format!(
+        "The profile `{profile}` is not defined, the available profiles are: {}",
+        profiles.join(", ")
+    ))
+}
+
+/// A command running the rustc that cargo will use, from `toolchain` if set
+fn rustc_command(toolchain: Option<&str>) -> Command {
+    match toolchain {
+        Some(toolchain) => {
+            let mut command = Command::new("rustc");
+            command.env("RUSTUP_TOOLCHAIN", toolchain);
+            command
+        }
+        None => Command::new(env::var_os("RUSTC").unwrap_or_else(|| "rustc".into())),
+    }
+}
+
+/// Returns true if the toolchain used within `dir` has the standard library for wasm32-unknown-unknown installed.
+/// If this can not be determined then true is returned and cargo is left to report any problem.
+fn is_wasm_target_installed(dir: &Path, toolchain: Option<&str>) -> bool {
+    match rustc_command(toolchain)
+        .current_dir(dir)
+        .args(["--print", "sysroot"])
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            let sysroot = String::from_utf8_lossy(&output.stdout);
+            Path::new(sysroot.trim())
+                .join("lib/rustlib/wasm32-unknown-unknown")
+                .is_dir()
+        }
+        _ => true,
+    }
+}
+
+/// The first line printed by `command --version` when run within `dir`
+fn command_version(mut command: Command, dir: &Path) -> String {
+    match command.current_dir(dir).arg("--version").output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or("")
+            .to_owned(),
+        Ok(output) => format!("`--version` failed with {}", output.status),
+        Err(err) => format!("failed to run: {err}"),
+    }
+}
+
+/// The version of `package` recorded in the Cargo.lock of the workspace
+fn locked_version(workspace_root: &Path, package: &str) -> Option<String> {
+    let lock = std::fs::read_to_string(workspace_root.join("Cargo.lock")).ok()?;
+    let lock: toml::value::Table = toml::from_str(&lock).ok()?;
+    let versions: Vec<&str> = lock
+        .get("package")?
+        .as_array()?
+        .iter()
+        .filter(|x| x.get("name").and_then(|x| x.as_str()) == Some(package))
+        .filter_map(|x| x.get("version").and_then(|x| x.as_str()))
+        .collect();
+    if versions.is_empty() {
+        None
+    } else {
+        Some(versions.join(", "))
+    }
+}
+
+/// Returns true if the toolchain used within `dir` allows unstable `-Z` flags
+fn is_nightly(dir: &Path, toolchain: Option<&str>) -> bool {
+    if env::var_os("RUSTC_BOOTSTRAP").is_some() {
+        return true;
+    }
+    rustc_command(toolchain)
+        .current_dir(dir)
+        .arg("-V")
+        .output()
+        .map(|output| {
+            let version = String::from_utf8_lossy(&output.stdout);
+            version.contains("-nightly") || version.contains("-dev")
+        })
+        .unwrap_or(false)
+}
+
+/// Format a command so that it can be copy pasted into a shell
+fn format_command(program: &Path, args: &[&OsStr]) -> String {
+    std::iter::once(program.as_os_str())
+        .chain(args.iter().copied())
+        .map(|arg| shell_quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote `arg` if needed so that a shell treats it as a single word
+fn shell_quote(arg: &str) -> String {
+    let needs_quotes = arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| !(c.is_ascii_alphanumeric() || "-_=./:+,@".contains(c)));
+    if needs_quotes {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    } else {
+        arg.to_owned()
+    }
+}
+
+/// Returns the wasm file listed in a cargo `compiler-artifact` message, if the message is for the target `name` of kind `kind`
+fn wasm_artifact(message: &serde_json::Value, name: &str, kind: &str) -> Option<PathBuf> {
+    if message.get("reason")?.as_str()? != "compiler-artifact" {
+        return None;
+    }
+
+    let target = message.get("target")?;
+    let is_kind = target
+        .get("kind")?
+        .as_array()?
+        .iter()
+        .any(|x| x.as_str() == Some(kind));
+    if target.get("name")?.as_str()? != name || !is_kind {
+        return None;
+    }
+
+    message
+        .get("filenames")?
+        .as_array()?
+        .iter()
+        .filter_map(|x| x.as_str())
+        .find(|x| x.ends_with(".wasm"))
+        .map(PathBuf::from)
+}
+
+/// Controls the `Cache-Control` headers sent by the dev server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Tell the browser to never cache any files, so that reloading the page always picks up the latest build.
+    NoCache,
+    /// Tell the browser to cache files with a content hash in their name indefinitely, emulating how they would be served in production.
+    /// e.g. `app-3f2a9c1d.js`, as produced by bundlers or a post build hook that renames the generated files.
+    /// Other files such as index.html are revalidated on every request with `no-cache`.
+    Immutable,
+}
+
+/// Where the wasm is run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Runtime {
+    /// Serve a page that runs the wasm from the dev server, for opening in a browser
+    Browser,
+    /// Execute the wasm with `node`
+    Node,
+    /// Execute the wasm with `deno`
+    Deno,
+}
+
+impl Runtime {
+    /// Run the js glue generated for this runtime
+    fn execute(self, js: &Path) -> Result<(), RunWasmError> {
+        let mut command = match self {
+            Runtime::Browser => unreachable!("the browser runtime is served by the dev server"),
+            Runtime::Node => Command::new("node"),
+            Runtime::Deno => {
+                let mut command = Command::new("deno");
+                // The generated glue reads the wasm file from disk
+                command.args(["run", "--allow-read"]);
+                command
+            }
+        };
+        let status = command
+            .arg(js)
+            .status()
+            .map_err(|err| format!("Failed to launch {:?}: {err}", command.get_program()))?;
+        if !status.success() {
+            return Err(RunWasmError::RuntimeExited {
+                code: status.code().unwrap_or(EXIT_FAILURE),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The text direction of the served webpage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+    Auto,
+}
+
+impl TextDirection {
+    fn as_str(self) -> &'static str {
+        match self {
+            TextDirection::Ltr => "ltr",
+            TextDirection::Rtl => "rtl",
+            TextDirection::Auto => "auto",
+        }
+    }
+}
+
+/// The IP version used by the dev server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+/// When the js glue generated by wasm-bindgen uses `TextEncoder.encodeInto`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodeInto {
+    /// Use `encodeInto` if the browser supports it, otherwise fall back to `encode`
+    Test,
+    /// Always use `encodeInto`, for when every targeted browser supports it
+    Always,
+    /// Never use `encodeInto`
+    Never,
+}
+
+/// A target that run-wasm will build
+struct BuildTarget {
+    name: String,
+    kind: TargetKind,
+}
+
+#[derive(Clone, Copy)]
+enum TargetKind {
+    Example,
+    Bin,
+    /// The default binary of the package, selected via `--package` alone
+    Package,
+}
+
+impl TargetKind {
+    /// The kind of the target as reported by cargo
+    fn cargo_kind(self) -> &'static str {
+        match self {
+            TargetKind::Example => "example",
+            TargetKind::Bin | TargetKind::Package => "bin",
+        }
+    }
+}
+
+/// The targets that will be built and where their files will be written, as returned by [`RunWasm::resolve`]
+pub struct ResolvedTargets {
+    /// The directory containing the output directory of each target
+    pub dir: PathBuf,
+    pub targets: Vec<ResolvedTarget>,
+}
+
+/// A single target returned by [`RunWasm::resolve`]
+pub struct ResolvedTarget {
+    /// Name of the binary or example
+    pub name: String,
+    /// The wasm file that cargo is expected to produce, or the prebuilt wasm file
+    pub wasm_source: PathBuf,
+    /// The directory the generated files will be written into
+    pub dest: PathBuf,
+    kind: TargetKind,
+}
+
+/// The files generated by [`RunWasm::build`]
+pub struct BuildOutput {
+    /// The directory served by the webserver
+    pub dir: PathBuf,
+    /// The index.html served at the root of `dir`.
+    /// When multiple targets are built this is a page linking to each target.
+    pub html: PathBuf,
+    /// A unique id for this build, included in each generated page as `<meta name="run-wasm-build-id">`.
+    /// Automated tests can compare this against the loaded page to confirm they are not looking at a stale build.
+    pub build_id: String,
+    /// The files generated for each target that was built
+    pub targets: Vec<TargetOutput>,
+}
+
+impl BuildOutput {
+    fn print_paths(&self) {
+        let print = |key: &str, path: &Path| {
+            println!("{key}={}", shell_quote(&path.to_string_lossy()));
+        };
+        if let [target] = self.targets.as_slice() {
+            print("WASM", &target.wasm);
+            print("JS", &target.js);
+            print("HTML", &target.html);
+            print("DIR", &target.dir);
+        } else {
+            print("HTML", &self.html);
+            print("DIR", &self.dir);
+            for target in &self.targets {
+                // Shell variable names can only contain alphanumerics and underscores
+                let prefix: String = target
+                    .name
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                print(&format!("{prefix}_WASM"), &target.wasm);
+                print(&format!("{prefix}_JS"), &target.js);
+                print(&format!("{prefix}_HTML"), &target.html);
+                print(&format!("{prefix}_DIR"), &target.dir);
+            }
+        }
+    }
+
+    fn single(target: TargetOutput, build_id: String) -> Self {
+        BuildOutput {
+            dir: target.dir.clone(),
+            html: target.html.clone(),
+            build_id,
+            targets: vec![target],
+        }
+    }
+}
+
+/// The files generated for a single target by [`RunWasm::build`]
+pub struct TargetOutput {
+    /// Name of the binary or example that was built
+    pub name: String,
+    /// The directory the files for this target were generated into
+    pub dir: PathBuf,
+    /// The wasm file output by wasm-bindgen
+    pub wasm: PathBuf,
+    /// The js glue output by wasm-bindgen
+    pub js: PathBuf,
+    /// The generated index.html, this is only generated for `Runtime::Browser`
+    pub html: PathBuf,
+}
+
+/// High-level function that can be called as your entire run-wasm application.
+///
+/// It will:
+/// 1. Get CLI args from env
+/// 2. Compile the rust project to wasm
+/// 3. Run wasm-bindgen
+/// 4. Generate an index.html that runs the wasm
+/// 5. Launch a tiny webserver to serve index.html + your wasm
+///
+/// It will block forever to keep the webserver running until killed with ctrl-c or similar
+///
+/// On failure the process exits with a non-zero exit code, so that scripts and CI can detect it:
+/// * 1 - building or serving failed
+/// * 2 - the command line arguments or `run-wasm.toml` are invalid
+///
+/// The css argument will be included directly into a `<style type="text/css"></style>` element in the generated page.
+/// By default the body element will include some margin, so for full page apps you will want to remove that by calling like:
+/// ```no_run
+///     cargo_run_wasm::run_wasm_cli_with_css("body { margin: 0px; }");
 /// ```
 pub fn run_wasm_cli_with_css(css: &str) {
-    let args = match Args::from_env() {
-        Ok(args) => args,
+    match RunWasmArgs::from_env() {
+        Ok(args) => run_wasm_cli_with_args(css, args),
         Err(err) => {
             println!("{}\n\n{}", err, HELP);
-            return;
+            std::process::exit(EXIT_INVALID_ARGS);
         }
-    };
+    }
+}
+
+/// The same as [`run_wasm_cli_with_css`] but with arguments that have already been parsed, instead of reading them from env.
+///
+/// This is useful when run-wasm is one command of a larger CLI that has already consumed the process arguments.
+/// Exits the process on failure in the same way as [`run_wasm_cli_with_css`].
+/// ```no_run
+/// # use cargo_run_wasm::{run_wasm_cli_with_args, RunWasmArgs};
+/// let args = RunWasmArgs::from_vec(vec!["--example".into(), "hello".into()]).unwrap();
+/// run_wasm_cli_with_args("body { margin: 0px; }", args);
+/// ```
+pub fn run_wasm_cli_with_args(css: &str, mut args: RunWasmArgs) {
     if args.help {
         println!("{}", HELP);
         return;
     }
+    if args.version {
+        println!(
+            "cargo-run-wasm {} (wasm-bindgen {})",
+            env!("CARGO_PKG_VERSION"),
+            wasm_bindgen_shared::version()
+        );
+        return;
+    }
+    color::init(args.color);
+    for option in &args.ignored_options {
+        println!(
+            "{} {option} has no effect when --build-only is used as the dev server is not started",
+            color::warning("warning:")
+        );
+    }
+
+    let mut css = css.to_owned();
+    // If the workspace can not be found then there is no config to load, building will report the actual problem.
+    let cargo_flags = cargo_flags(args.locked, args.offline, args.frozen);
+    let cargo = match args.toolchain {
+        Some(_) => PathBuf::from("cargo"),
+        None => default_cargo_executable(),
+    };
+    if let Ok(directories) = CargoDirectories::new(
+        &cargo,
+        args.package_path.as_deref(),
+        &cargo_flags,
+        args.toolchain.as_deref(),
+    ) {
+        match Config::load(&directories.workspace_root) {
+            Ok(Some(config)) => {
+                if let Err(err) = args.apply_config(config, &mut css) {
+                    println!("{} {err}", color::error("error:"));
+                    std::process::exit(EXIT_INVALID_ARGS);
+                }
+            }
+            Ok(None) => {}
+            Err(err) => {
+                println!("{} {err}", color::error("error:"));
+                std::process::exit(EXIT_INVALID_ARGS);
+            }
+        }
+    }
 
-    if let Err(err) = RunWasm::new()
-        .with_css(css)
-        .with_package(args.package)
+    // RunWasm passes the verbosity on to cargo as well as applying it to its own output
+    let quiet = args.build_args.iter().any(|x| x == "-q" || x == "--quiet");
+    let verbose: usize = args.build_args.iter().map(|x| verbose_count(x)).sum();
+    args.build_args
+        .retain(|x| x != "-q" && x != "--quiet" && verbose_count(x) == 0);
+    let mut run_wasm = RunWasm::new()
+        .with_css(&css)
+        .with_packages(args.packages)
         .with_example(args.example)
         .with_bin(args.bin)
+        .with_sole_example(args.sole_example)
+        .with_sole_bin(args.sole_bin)
+        .with_all_examples(args.all_examples)
         .with_profile(args.profile)
+        .with_locked(args.locked)
+        .with_offline(args.offline)
+        .with_frozen(args.frozen)
+        .with_keep_going(args.keep_going)
+        .with_features(args.features)
         .with_cargo_build_args(args.build_args)
         .with_build_only(args.build_only)
+        .with_print_paths(args.print_paths)
+        .with_demangle(args.demangle)
+        .with_split_linked_modules(args.split_linked_modules)
+        .with_echo_html(args.echo_html)
+        .with_fullscreen_canvas(args.fullscreen_canvas)
+        .with_html_only(args.html_only)
+        .with_inline_wasm(args.inline_wasm)
+        .with_runtime(args.runtime)
+        .with_shared_target_dir(args.shared_target_dir)
+        .with_clean_output(args.clean_output)
+        .with_wasm_rustflags(args.wasm_rustflags)
+        .with_target_features(args.target_features)
         .with_host(args.host)
         .with_port(args.port)
-        .run()
-    {
-        println!("{err}")
+        .with_address_family(args.address_family)
+        .with_cache_policy(args.cache_policy)
+        .with_spa_fallback(args.spa_fallback)
+        .with_access_log(args.access_log)
+        .with_live_reload(args.live_reload)
+        .with_allowed_hosts(args.allowed_hosts)
+        .with_open(args.open)
+        .with_force_open(args.force_open)
+        .with_quiet(quiet)
+        .with_verbose(verbose.min(u8::MAX as usize) as u8)
+        .with_serve_timeout(args.serve_timeout);
+    if let Some(wasm_bindgen) = args.external_bindgen {
+        run_wasm = run_wasm.with_external_bindgen(wasm_bindgen);
+    }
+    if let Some(encode_into) = args.encode_into {
+        run_wasm = run_wasm.with_encode_into(encode_into);
+    }
+    for (prefix, target) in &args.proxies {
+        run_wasm = run_wasm.with_proxy(prefix, target);
+    }
+    for static_dir in args.static_dirs {
+        run_wasm = run_wasm.with_static_dir(static_dir);
+    }
+    if let Some(log_level) = &args.log_level {
+        run_wasm = run_wasm.with_log_level(log_level);
     }
+    if let Some(base_path) = &args.base_path {
+        run_wasm = run_wasm.with_base_path(base_path);
+    }
+    if let Some(bundle) = args.bundle {
+        run_wasm = run_wasm.with_bundle(bundle);
+    }
+    if let Some(output_dir) = args.output_dir {
+        run_wasm = run_wasm.with_output_dir(output_dir);
+    }
+    if let Some(toolchain) = &args.toolchain {
+        run_wasm = run_wasm.with_toolchain(toolchain);
+    }
+    if let Some(package_path) = args.package_path {
+        run_wasm = run_wasm.with_package_path(package_path);
+    }
+    let result = if args.print_config {
+        run_wasm.print_config()
+    } else if args.bindgen_info {
+        run_wasm.print_bindgen_info()
+    } else {
+        run_wasm.run()
+    };
+    match result {
+        Ok(()) => {}
+        // Pass on the exit code of the wasm, as if it had been run directly
+        Err(RunWasmError::RuntimeExited { code }) => std::process::exit(code),
+        Err(err) => {
+            println!("{} {err}", color::error("error:"));
+            std::process::exit(match err {
+                RunWasmError::InvalidConfig { .. } => EXIT_INVALID_ARGS,
+                _ => EXIT_FAILURE,
+            });
+        }
+    }
+}
+
+/// The number of times `arg` passes cargo's verbose flag, e.g. 2 for `-vv`
+fn verbose_count(arg: &str) -> usize {
+    match arg.strip_prefix('-') {
+        Some("-verbose") => 1,
+        Some(flags) if !flags.is_empty() && flags.chars().all(|c| c == 'v') => flags.len(),
+        _ => 0,
+    }
+}
+
+/// What cargo reported about the workspace, kept for the duration of a single `run()`, `build()` or `validate()`
+/// so that the slow cargo invocations are only made once.
+#[derive(Default)]
+struct Workspace {
+    directories: Option<CargoDirectories>,
+    metadata: Option<CargoMetadata>,
+}
+
+/// A change to the rustflags of the wasm build
+enum Rustflags {
+    /// Replace the rustflags with these, via the `RUSTFLAGS` env var
+    Env(String),
+    /// Replace the rustflags with these, via the `CARGO_ENCODED_RUSTFLAGS` env var
+    Encoded(String),
+    /// Add to the rustflags of the cargo config, via this `--config` argument
+    Config(String),
 }
 
+/// The exit code used when building or serving fails
+const EXIT_FAILURE: i32 = 1;
+/// The exit code used when the arguments are invalid
+const EXIT_INVALID_ARGS: i32 = 2;
+
 #[deprecated(note = "renamed to run_wasm_cli_with_css")]
 pub fn run_wasm_with_css(css: &str) {
     run_wasm_cli_with_css(css)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_wasm_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RunWasm>();
+    }
+
+    fn args_error(args: &[&str]) -> String {
+        match RunWasmArgs::from_vec(args.iter().map(OsString::from).collect()) {
+            Ok(_) => panic!("The arguments {:?} should have been rejected", args),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn banned_options() {
+        for args in [
+            &["--target", "x86_64-unknown-linux-gnu"][..],
+            &["--target=x86_64-unknown-linux-gnu"],
+            &["--example", "foo", "--target-dir", "dir"],
+            &["--target-dir=dir", "--release"],
+            &["--message-format=json"],
+        ] {
+            assert!(args_error(args).starts_with("cargo-run-wasm does not support the"));
+        }
+        assert_eq!(
+            args_error(&["--target=x", "--target-dir", "dir"]),
+            "cargo-run-wasm does not support the --target or --target-dir option"
+        );
+        // Only the exact option is banned, not other options sharing its prefix
+        assert!(RunWasmArgs::from_vec(vec!["--target-feature".into(), "simd128".into()]).is_ok());
+    }
+
+    #[test]
+    fn conflicting_and_invalid_arguments() {
+        assert!(args_error(&["--release", "--profile", "dev"])
+            .starts_with("conflicting usage of --profile and --release"));
+        assert!(
+            args_error(&["--ipv4", "--ipv6"]).starts_with("conflicting usage of --ipv4 and --ipv6")
+        );
+        assert!(args_error(&["--no-cache", "--immutable-cache"])
+            .starts_with("conflicting usage of --no-cache and --immutable-cache"));
+        assert!(args_error(&["--proxy", "/api"]).starts_with("Invalid --proxy `/api`"));
+        assert!(args_error(&["--proxy", "/api=https://localhost:3000"])
+            .starts_with("Invalid --proxy `/api=https://localhost:3000`"));
+        assert!(args_error(&["--color", "sometimes"]).starts_with("Invalid --color `sometimes`"));
+        assert!(args_error(&["--runtime", "bun"]).starts_with("Invalid --runtime `bun`"));
+        assert!(
+            args_error(&["--encode-into", "maybe"]).starts_with("Invalid --encode-into `maybe`")
+        );
+    }
+
+    #[test]
+    fn build_only_ignored_options() {
+        let args = RunWasmArgs::from_vec(
+            ["--build-only", "--port", "8080", "--spa"]
+                .iter()
+                .map(OsString::from)
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(args.ignored_options, ["--port", "--spa"]);
+        let args = RunWasmArgs::from_vec(vec!["--spa".into()]).unwrap();
+        assert!(args.ignored_options.is_empty());
+    }
+
+    #[test]
+    fn missing_and_malformed_values() {
+        assert!(args_error(&["--example", "foo", "--port"]).starts_with("Invalid --port:"));
+        assert!(args_error(&["--profile"]).starts_with("Invalid --profile:"));
+        assert!(args_error(&["--serve-timeout", "soon"]).starts_with("Invalid --serve-timeout:"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStringExt;
+            let args = vec![
+                OsString::from("--port"),
+                OsString::from_vec(vec![0x38, 0x30, 0xff]),
+            ];
+            assert!(
+                matches!(RunWasmArgs::from_vec(args), Err(err) if err.starts_with("Invalid --port:"))
+            );
+        }
+    }
+
+    #[test]
+    fn served_url_includes_base_path() {
+        for base_path in ["app", "/app", "/app/", "app/"] {
+            assert_eq!(RunWasm::new().with_base_path(base_path).base_path, "/app/");
+        }
+        assert_eq!(RunWasm::new().with_base_path("/").base_path, "/");
+        assert_eq!(RunWasm::new().base_path, "/");
+        assert_eq!(
+            server_url("localhost", 8000, "/app/"),
+            "http://localhost:8000/app/"
+        );
+        assert_eq!(server_url("::1", 8000, "/"), "http://[::1]:8000/");
+    }
+
+    #[test]
+    fn proxy_targets() {
+        assert_eq!(proxy_address("http://localhost:3000"), Ok("localhost:3000"));
+        assert_eq!(
+            proxy_address("http://localhost:3000/"),
+            Ok("localhost:3000")
+        );
+        assert!(proxy_address("localhost:3000").is_err());
+        assert!(proxy_address("https://localhost:3000").is_err());
+        assert!(proxy_address("http://localhost:3000/api").is_err());
+    }
+
+    #[test]
+    fn invalid_proxy_is_a_settings_error() {
+        let run_wasm = RunWasm::new()
+            .with_proxy("/api", "http://localhost:3000")
+            .with_proxy("/ws", "localhost:3001");
+        assert_eq!(run_wasm.proxies.len(), 1);
+        assert_eq!(
+            run_wasm.settings_errors(),
+            ["The proxy target `localhost:3001` must be a url starting with `http://`"]
+        );
+    }
+
+    #[test]
+    fn features() {
+        let split: Vec<String> = split_features("pkg/feat, dep:serde  a,b").collect();
+        assert_eq!(split, ["pkg/feat", "dep:serde", "a", "b"]);
+
+        let args = RunWasmArgs::from_vec(
+            ["-F", "a b", "--features", "pkg/feat,dep:serde"]
+                .iter()
+                .map(OsString::from)
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(args.features, ["pkg/feat", "dep:serde", "a", "b"]);
+    }
+
+    #[test]
+    fn missing_index() {
+        let dir = std::env::temp_dir().join(format!("run-wasm-index-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let index = dir.join("index.html");
+        assert!(check_index(&index).is_err());
+        std::fs::write(&index, "").unwrap();
+        assert!(check_index(&index).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn jobs() {
+        let target = ResolvedTarget {
+            name: "foo".to_owned(),
+            wasm_source: PathBuf::new(),
+            dest: PathBuf::new(),
+            kind: TargetKind::Example,
+        };
+        assert_eq!(
+            RunWasm::new().with_jobs(4).cargo_target_args(&target),
+            ["--example", "foo", "--jobs", "4"]
+        );
+        assert!(!RunWasm::new()
+            .cargo_target_args(&target)
+            .contains(&"--jobs".to_owned()));
+        assert!(!RunWasm::new().with_jobs(0).settings_errors().is_empty());
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(is_pattern("demo_*"));
+        assert!(is_pattern("demo_?"));
+        assert!(!is_pattern("demo"));
+
+        assert!(pattern_matches("demo_*", "demo_"));
+        assert!(pattern_matches("demo_*", "demo_cube"));
+        assert!(pattern_matches("*_cube", "demo_cube"));
+        assert!(pattern_matches("d*o*e", "demo_cube"));
+        assert!(pattern_matches("demo_???e", "demo_cube"));
+        assert!(pattern_matches("*", ""));
+        assert!(!pattern_matches("demo_*", "demo"));
+        assert!(!pattern_matches("demo_?", "demo_cube"));
+        assert!(!pattern_matches("*_cube", "demo_cubes"));
+    }
+
+    #[test]
+    fn flag_without_value() {
+        let os_args =
+            |args: &[&str]| -> Vec<OsString> { args.iter().map(OsString::from).collect() };
+
+        let mut args = os_args(&["--example", "--release"]);
+        assert!(remove_flag_without_value(&mut args, "--example"));
+        assert_eq!(args, os_args(&["--release"]));
+
+        let mut args = os_args(&["--release", "--example"]);
+        assert!(remove_flag_without_value(&mut args, "--example"));
+        assert_eq!(args, os_args(&["--release"]));
+
+        let mut args = os_args(&["--example", "foo"]);
+        assert!(!remove_flag_without_value(&mut args, "--example"));
+        assert_eq!(args, os_args(&["--example", "foo"]));
+
+        let mut args = os_args(&["--bin", "foo"]);
+        assert!(!remove_flag_without_value(&mut args, "--example"));
+    }
+
+    #[test]
+    fn package_path() {
+        let dir = std::env::temp_dir().join(format!("run-wasm-package-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"other\"\n").unwrap();
+        let target = ResolvedTarget {
+            name: "other".to_owned(),
+            wasm_source: PathBuf::new(),
+            dest: PathBuf::new(),
+            kind: TargetKind::Package,
+        };
+        let run_wasm = RunWasm::new().with_package_path(dir.clone());
+        let manifest_path = dir
+            .join("Cargo.toml")
+            .into_os_string()
+            .into_string()
+            .unwrap();
+        assert_eq!(
+            run_wasm.cargo_target_args(&target),
+            ["--manifest-path", &manifest_path, "--package", "other"]
+        );
+        assert!(run_wasm.settings_errors().is_empty());
+
+        // Selecting a package by name as well is rejected regardless of the order
+        let run_wasm = RunWasm::new()
+            .with_package_path(dir.clone())
+            .with_package(Some("foo".to_owned()));
+        assert_eq!(run_wasm.settings_errors().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        let run_wasm = RunWasm::new().with_package_path(dir);
+        assert!(run_wasm.settings_errors()[0].starts_with("No package found at"));
+    }
+
+    #[test]
+    fn hyphenated_target_file_name() {
+        assert_eq!(wasm_file_name("my-example"), "my_example.wasm");
+        assert_eq!(wasm_file_name("my_example"), "my_example.wasm");
+    }
+
+    /// The error from resolving the targets, for settings that are rejected before cargo is asked anything
+    fn resolve_error(run_wasm: RunWasm) -> String {
+        match run_wasm.resolve_targets(
+            Path::new("cargo"),
+            Path::new("."),
+            &mut Workspace::default(),
+        ) {
+            Ok(_) => panic!("The targets should have been rejected"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn conflicting_example_and_bin() {
+        let err = resolve_error(
+            RunWasm::new()
+                .with_example(Some("foo".to_owned()))
+                .with_bin(Some("bar".to_owned())),
+        );
+        assert!(err.starts_with("conflicting usage of --example and --bin"));
+
+        // `--example` and `--bin` without a name conflict the same way
+        let err = resolve_error(RunWasm::new().with_sole_example(true).with_sole_bin(true));
+        assert!(err.starts_with("conflicting usage of --example and --bin"));
+    }
+
+    #[test]
+    fn conflicting_example_and_multiple_packages() {
+        let err = resolve_error(
+            RunWasm::new()
+                .with_packages(vec!["foo".to_owned(), "bar".to_owned()])
+                .with_example(Some("baz".to_owned())),
+        );
+        assert!(err.starts_with("conflicting usage of multiple --package"));
+    }
+
+    #[test]
+    fn live_reload_script_placeholders() {
+        let script = template::render(LIVE_RELOAD_SCRIPT, &[("path", server::LIVE_RELOAD_PATH)]);
+        assert!(script.contains(server::LIVE_RELOAD_PATH));
+        assert!(!script.contains("{{"));
+    }
+}
@@ -0,0 +1,212 @@
+//! Query the workspace layout and the targets declared by its packages via `cargo metadata`
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The subset of `cargo metadata --no-deps --format-version=1` output that run-wasm uses
+#[derive(Deserialize)]
+pub struct CargoMetadata {
+    pub workspace_root: PathBuf,
+    pub target_directory: PathBuf,
+    pub packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<Target>,
+}
+
+#[derive(Deserialize)]
+pub struct Target {
+    pub name: String,
+    pub kind: Vec<String>,
+}
+
+/// Why `cargo metadata` failed
+pub struct MetadataError {
+    pub message: String,
+    /// cargo could not be run or printed something other than the metadata, which may not happen again.
+    /// Otherwise cargo itself rejected the workspace, e.g. due to an invalid manifest, and will do so every time.
+    pub transient: bool,
+}
+
+impl CargoMetadata {
+    /// Run `cargo metadata` for the workspace containing `dir`.
+    /// `cargo_flags` are the `--locked`, `--offline` and `--frozen` flags the user passed, so cargo does not reach the network against their wishes,
+    /// along with any `--manifest-path`.
+    /// `toolchain` selects the rustup toolchain the same way `cargo +toolchain` does.
+    pub fn new(
+        cargo_executable: &Path,
+        dir: &Path,
+        cargo_flags: &[&str],
+        toolchain: Option<&str>,
+    ) -> Result<Self, String> {
+        Self::fetch(cargo_executable, dir, cargo_flags, toolchain).map_err(|err| err.message)
+    }
+
+    /// Like `new`, but reports whether the failure is worth retrying
+    pub fn fetch(
+        cargo_executable: &Path,
+        dir: &Path,
+        cargo_flags: &[&str],
+        toolchain: Option<&str>,
+    ) -> Result<Self, MetadataError> {
+        let mut command = Command::new(cargo_executable);
+        command
+            .current_dir(dir)
+            .args(["metadata", "--no-deps", "--format-version=1"])
+            .args(cargo_flags);
+        if let Some(toolchain) = toolchain {
+            command.env("RUSTUP_TOOLCHAIN", toolchain);
+        }
+        let output = command.output().map_err(|err| MetadataError {
+            message: format!("Failed to run `cargo metadata`: {err}"),
+            transient: true,
+        })?;
+        if !output.status.success() {
+            return Err(MetadataError {
+                message: format!(
+                    "`cargo metadata` failed:\n{}",
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+                transient: false,
+            });
+        }
+
+        // Some cargo versions and configurations print extra lines to stdout, so only parse the line containing the json
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let json = stdout
+            .lines()
+            .find(|line| line.starts_with('{'))
+            .unwrap_or(&stdout);
+        serde_json::from_str(json).map_err(|err| {
+            let preview: Vec<&str> = stdout
+                .lines()
+                .take(5)
+                .map(|line| line.get(..200).unwrap_or(line))
+                .collect();
+            MetadataError {
+                message: format!(
+                    "Failed to parse `cargo metadata` output: {err}\nstdout started with:\n{}\nstderr:\n{}",
+                    preview.join("\n"),
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+                transient: true,
+            }
+        })
+    }
+
+    /// The names of all targets of `kind` in `package`, or in the whole workspace if `package` is `None`.
+    pub fn targets(&self, package: Option<&str>, kind: &str) -> Vec<&str> {
+        self.packages
+            .iter()
+            .filter(|x| package.map(|package| x.name == package).unwrap_or(true))
+            .flat_map(|x| x.targets.iter())
+            .filter(|x| x.kind.iter().any(|x| x == kind))
+            .map(|x| x.name.as_str())
+            .collect()
+    }
+
+    /// Returns true if `manifest_path` is a virtual manifest, one with a `[workspace]` but no `[package]`.
+    /// With `--no-deps` the packages are exactly the workspace members, so none of them are declared by a virtual manifest.
+    pub fn is_virtual(&self, manifest_path: &Path) -> bool {
+        let manifest_path = manifest_path
+            .canonicalize()
+            .unwrap_or_else(|_| manifest_path.to_owned());
+        !self
+            .packages
+            .iter()
+            .any(|x| x.manifest_path == manifest_path)
+    }
+
+    /// Check that `package` has no more than one bin target, as otherwise cargo needs `--bin` to know which one to run
+    pub fn check_single_bin(&self, package: &str) -> Result<(), String> {
+        let bins = self.targets(Some(package), "bin");
+        if bins.len() > 1 {
+            return Err(format!(
+                "`--package {package}` was used but the package has multiple bin targets, select one with `--bin NAME`:\n{}",
+                bins.join("\n")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Find the only target of `kind` in `package`, or in the whole workspace if `package` is `None`.
+    pub fn sole_target(&self, package: Option<&str>, kind: &str) -> Result<String, String> {
+        let targets = self.targets(package, kind);
+
+        match targets.as_slice() {
+            [target] => Ok(target.to_string()),
+            [] => Err(format!(
+                "`--{kind}` was used without a name but there are no {kind} targets available"
+            )),
+            _ => Err(format!(
+                "`--{kind}` was used without a name but there are multiple {kind} targets available, specify one of:\n{}",
+                targets.join("\n")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed down output of `cargo metadata --no-deps --format-version=1`, keeping some of the fields run-wasm ignores
+    const SAMPLE: &str = r#"{
+        "packages": [
+            {
+                "name": "app",
+                "version": "0.1.0",
+                "manifest_path": "/work/app/Cargo.toml",
+                "targets": [
+                    {"name": "app", "kind": ["bin"], "src_path": "/work/app/src/main.rs"},
+                    {"name": "demo-one", "kind": ["example"], "src_path": "/work/app/examples/demo-one.rs"}
+                ]
+            },
+            {
+                "name": "tools",
+                "version": "0.1.0",
+                "manifest_path": "/work/tools/Cargo.toml",
+                "targets": [
+                    {"name": "tools", "kind": ["lib"], "src_path": "/work/tools/src/lib.rs"},
+                    {"name": "a", "kind": ["bin"], "src_path": "/work/tools/src/bin/a.rs"},
+                    {"name": "b", "kind": ["bin"], "src_path": "/work/tools/src/bin/b.rs"}
+                ]
+            }
+        ],
+        "workspace_members": ["app 0.1.0 (path+file:///work/app)", "tools 0.1.0 (path+file:///work/tools)"],
+        "target_directory": "/work/target",
+        "version": 1,
+        "workspace_root": "/work"
+    }"#;
+
+    #[test]
+    fn parse_sample() {
+        let metadata: CargoMetadata = serde_json::from_str(SAMPLE).unwrap();
+        assert_eq!(metadata.workspace_root, Path::new("/work"));
+        assert_eq!(metadata.target_directory, Path::new("/work/target"));
+        assert_eq!(metadata.packages.len(), 2);
+        assert_eq!(
+            metadata.packages[0].manifest_path,
+            Path::new("/work/app/Cargo.toml")
+        );
+    }
+
+    #[test]
+    fn targets() {
+        let metadata: CargoMetadata = serde_json::from_str(SAMPLE).unwrap();
+        assert_eq!(metadata.targets(None, "bin"), ["app", "a", "b"]);
+        assert_eq!(metadata.targets(Some("tools"), "bin"), ["a", "b"]);
+        assert_eq!(metadata.targets(None, "example"), ["demo-one"]);
+        assert_eq!(
+            metadata.sole_target(None, "example").unwrap(),
+            "demo-one".to_owned()
+        );
+        assert!(metadata.sole_target(Some("tools"), "bin").is_err());
+        assert!(metadata.check_single_bin("app").is_ok());
+        assert!(metadata.check_single_bin("tools").is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! A minimal minifier for the js glue generated by wasm-bindgen
+//!
+//! The glue is mostly documentation comments and indentation, so removing those gets most of the benefit of a real minifier
+//! without needing to parse javascript.
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A zero based line and column, in utf-16 code units as used by source maps
+pub type Position = (u32, u32);
+
+pub struct Minified {
+    pub code: String,
+    /// For each line of `code`, the position in the source of the first character on it.
+    /// None for lines that are empty, which only occurs within multiline string literals.
+    pub lines: Vec<Option<Position>>,
+}
+
+/// Remove comments, indentation and blank lines from `source`.
+/// Line breaks are kept so that automatic semicolon insertion is unaffected.
+pub fn minify_js(source: &str) -> Minified {
+    let mut result = Output {
+        code: String::with_capacity(source.len()),
+        lines: vec![None],
+    };
+    let mut chars = Source {
+        chars: source.chars().peekable(),
+        line: 0,
+        column: 0,
+        position: (0, 0),
+    };
+    // The quote of the string or template literal currently being copied
+    let mut quote = None;
+    // For each `${` substitution of a template literal that is open, how many braces have been opened within it
+    let mut substitutions: Vec<u32> = vec![];
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            result.push(c, chars.position);
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    result.push(escaped, chars.position);
+                }
+            } else if c == q {
+                quote = None;
+            } else if q == '`' && c == '$' && chars.peek() == Some(&'{') {
+                // The substitution is code, which may itself contain comments and literals
+                chars.next();
+                result.push('{', chars.position);
+                substitutions.push(0);
+                quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' | '`' => {
+                quote = Some(c);
+                result.push(c, chars.position);
+                line_start = false;
+            }
+            '{' => {
+                if let Some(depth) = substitutions.last_mut() {
+                    *depth += 1;
+                }
+                result.push(c, chars.position);
+                line_start = false;
+            }
+            '}' => {
+                match substitutions.last_mut() {
+                    // The end of a substitution, the rest of the template literal follows
+                    Some(0) => {
+                        substitutions.pop();
+                        quote = Some('`');
+                    }
+                    Some(depth) => *depth -= 1,
+                    None => {}
+                }
+                result.push(c, chars.position);
+                line_start = false;
+            }
+            // e.g. a unicode escape within an identifier
+            '\\' => {
+                result.push(c, chars.position);
+                if let Some(escaped) = chars.next() {
+                    result.push(escaped, chars.position);
+                }
+                line_start = false;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut newline = false;
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    newline |= c == '\n';
+                    previous = c;
+                }
+                // Keep the tokens on either side of the comment apart
+                if !line_start {
+                    result.push(if newline { '\n' } else { ' ' }, chars.position);
+                    line_start = newline;
+                }
+            }
+            '/' if starts_regex(&result.code) => {
+                result.push(c, chars.position);
+                line_start = false;
+                // A `/` within a character class does not end the regex
+                let mut class = false;
+                while let Some(c) = chars.next() {
+                    result.push(c, chars.position);
+                    match c {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                result.push(escaped, chars.position);
+                            }
+                        }
+                        '[' => class = true,
+                        ']' => class = false,
+                        '/' if !class => break,
+                        // Not a valid regex, leave the rest of the line to be handled as usual
+                        '\n' => {
+                            line_start = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ' ' | '\t' | '\r' if line_start => {}
+            '\n' => {
+                if !line_start {
+                    result.push('\n', chars.position);
+                    line_start = true;
+                }
+            }
+            c => {
+                result.push(c, chars.position);
+                line_start = false;
+            }
+        }
+    }
+    Minified {
+        code: result.code,
+        lines: result.lines,
+    }
+}
+
+/// Returns true if a `/` following `code` starts a regex literal rather than being a division.
+/// That is the case after an operator or keyword, but not after a value such as an identifier, literal or closing bracket.
+fn starts_regex(code: &str) -> bool {
+    let code = code.trim_end();
+    let word_start = code
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let word = &code[word_start..];
+    if !word.is_empty() {
+        const KEYWORDS: &[&str] = &[
+            "return",
+            "typeof",
+            "instanceof",
+            "in",
+            "of",
+            "new",
+            "delete",
+            "void",
+            "throw",
+            "case",
+            "do",
+            "else",
+            "yield",
+            "await",
+        ];
+        return KEYWORDS.contains(&word);
+    }
+    !code.ends_with(&[')', ']', '}', '"', '\'', '`'][..])
+}
+
+struct Output {
+    code: String,
+    lines: Vec<Option<Position>>,
+}
+
+impl Output {
+    /// Append `c`, which was at `position` in the source
+    fn push(&mut self, c: char, position: Position) {
+        if c == '\n' {
+            self.lines.push(None);
+        } else if let Some(line) = self.lines.last_mut() {
+            line.get_or_insert(position);
+        }
+        self.code.push(c);
+    }
+}
+
+/// The characters of the source, tracking the position of the last one returned
+struct Source<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: u32,
+    column: u32,
+    position: Position,
+}
+
+impl Source<'_> {
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+}
+
+impl Iterator for Source<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.position = (self.line, self.column);
+        if c == '\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += c.len_utf16() as u32;
+        }
+        Some(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minify(source: &str) -> String {
+        minify_js(source).code
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            minify("/** docs\n * more docs\n */\nlet a = 1; // trailing\nlet b = 2;\n"),
+            "let a = 1; \nlet b = 2;\n"
+        );
+        assert_eq!(minify("a /* inline */ + b"), "a   + b");
+        // A block comment spanning lines still separates the tokens with a line break
+        assert_eq!(minify("a /* one\ntwo */ b"), "a \nb");
+    }
+
+    #[test]
+    fn comments_within_strings() {
+        for source in [
+            "const url = \"http://localhost\";",
+            "const url = 'http://localhost';",
+            "const url = `http://localhost`;",
+            "const s = \"/* not a comment */\";",
+            "const s = \"escaped \\\" // quote\";",
+        ] {
+            assert_eq!(minify(source), source);
+        }
+    }
+
+    #[test]
+    fn regex_literals() {
+        for source in [
+            "const r = /\\/\\//g;",
+            "const r = /\"/;",
+            "const r = /'/;",
+            "const r = /[/'\"]/;",
+            "if (/`/.test(s)) {}",
+            "return /\\/\\/ \"/.test(s);",
+        ] {
+            assert_eq!(minify(source), source);
+        }
+        assert_eq!(minify("const r = /'/; // '"), "const r = /'/; ");
+        // Division is not mistaken for a regex
+        assert_eq!(minify("a / b // c"), "a / b ");
+        assert_eq!(
+            minify("f(a) / 2 / \"x\".length // c"),
+            "f(a) / 2 / \"x\".length "
+        );
+    }
+
+    #[test]
+    fn template_literals() {
+        for source in [
+            "`a ${b} c`",
+            "`a ${ `b ${c} d` } e`",
+            "`a ${ b ? `c` : \"d\" } e // f`",
+            "`a ${ { x: `}` }.x } b`",
+            "`a\n  b ${c}\n  d`",
+        ] {
+            assert_eq!(minify(source), source);
+        }
+        assert_eq!(minify("`a ${b /* c */} d`"), "`a ${b  } d`");
+        assert_eq!(minify("`a ${b // c\n} d`"), "`a ${b \n} d`");
+    }
+
+    #[test]
+    fn newlines_are_kept_for_semicolon_insertion() {
+        assert_eq!(minify("a\n++b"), "a\n++b");
+        assert_eq!(minify("return\n  x"), "return\nx");
+        assert_eq!(minify("a = 1\n\n\n  b = 2"), "a = 1\nb = 2");
+    }
+
+    #[test]
+    fn line_positions() {
+        let minified = minify_js("let a = 1;\n  // c\n  let b = 2;\n\tlet s = `x\n\ny`;\n");
+        assert_eq!(minified.code, "let a = 1;\nlet b = 2;\nlet s = `x\n\ny`;\n");
+        assert_eq!(
+            minified.lines,
+            [
+                Some((0, 0)),
+                Some((2, 2)),
+                Some((3, 1)),
+                None,
+                Some((5, 0)),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn columns_are_utf16() {
+        let minified = minify_js("'😀'; /* c */\n  x");
+        assert_eq!(minified.lines, [Some((0, 0)), Some((1, 2))]);
+        let minified = minify_js("/* 😀 */ x");
+        assert_eq!(minified.lines, [Some((0, 9))]);
+    }
+}
@@ -0,0 +1,731 @@
+//! A tiny webserver for serving the generated files
+//!
+//! This is implemented by hand rather than with a library as we need control over the response headers
+//! and only have to support the few requests a browser makes while loading the page.
+use crate::CachePolicy;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+pub struct Server {
+    pub dir: PathBuf,
+    pub cache_policy: CachePolicy,
+    pub spa_fallback: bool,
+    /// The file served when a directory is requested
+    pub index_file: String,
+    /// The path the files of `dir` are served below, starting and ending with `/`
+    pub base_path: String,
+    /// Requests with a path starting with the prefix are forwarded to the `host:port` address
+    pub proxies: Vec<(String, String)>,
+    /// Print the method, path and status of every request
+    pub access_log: bool,
+    /// Request paths that are served from a specific file instead of from `dir`
+    pub routes: Vec<(String, PathBuf)>,
+    /// Directories searched in order for the requested file before `dir`
+    pub static_dirs: Vec<PathBuf>,
+    /// Hosts accepted in the `Host` header of requests in addition to localhost and ip addresses, any host is accepted when empty
+    pub allowed_hosts: Vec<String>,
+    /// The json served at [`HEALTH_PATH`]
+    pub health: String,
+    /// Content types for file extensions, taking precedence over the built in ones
+    pub mime_types: Vec<(String, String)>,
+    /// Accept websocket connections at [`LIVE_RELOAD_PATH`] and tell them to reload when the files in `dir` change
+    pub live_reload: Option<LiveReload>,
+}
+
+/// Responds once the server is ready, so automation can poll it instead of sleeping.
+/// Namespaced to avoid colliding with the routes of the app.
+pub const HEALTH_PATH: &str = "/__run_wasm_health";
+
+/// The websocket the live reload client script connects to
+pub const LIVE_RELOAD_PATH: &str = "/__run_wasm_live_reload";
+
+/// How often `dir` is checked for changes when live reload is enabled
+const LIVE_RELOAD_POLL: Duration = Duration::from_millis(500);
+
+/// The websocket connections of the pages open in the browser
+#[derive(Default)]
+pub struct LiveReload {
+    clients: Mutex<Vec<Box<dyn Write + Send>>>,
+}
+
+impl LiveReload {
+    /// Complete the websocket handshake and keep the connection to notify later.
+    /// The client never sends anything we need, so the connection is never read from again.
+    fn accept<S: Stream>(&self, mut stream: S, request: &Request) -> io::Result<()> {
+        let key = request
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("sec-websocket-key"));
+        let key = match key {
+            Some((_, key)) => key,
+            None => {
+                let response = Response::text(400, "Expected a websocket connection");
+                return write_response(stream, request, response);
+            }
+        };
+        // Defined by RFC 6455 to prove the server understood the websocket handshake
+        let digest = sha1_smol::Sha1::from(format!("{key}258EAFA5-E914-47DA-95CA-C5AB0DC85B11"))
+            .digest()
+            .bytes();
+        let accept = crate::template::base64(&digest);
+        stream.write_all(
+            format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+            )
+            .as_bytes(),
+        )?;
+        stream.flush()?;
+        self.clients.lock().unwrap().push(Box::new(stream));
+        Ok(())
+    }
+
+    /// Tell every connected page to reload, dropping the connections of pages that have since closed
+    fn reload(&self) {
+        let message = b"reload";
+        // A final text frame, short enough for the length to fit in the second byte
+        let mut frame = vec![0x81, message.len() as u8];
+        frame.extend_from_slice(message);
+        self.clients.lock().unwrap().retain_mut(|client| {
+            client
+                .write_all(&frame)
+                .and_then(|_| client.flush())
+                .is_ok()
+        });
+    }
+}
+
+/// Where the server accepts connections from
+pub enum Listener {
+    Tcp(TcpListener),
+    /// For sandboxes that do not allow binding tcp ports, the server can be reached through a proxy or a client like `curl --unix-socket`
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener) => listener.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            Listener::Unix(listener) => listener.set_nonblocking(nonblocking),
+        }
+    }
+}
+
+/// A connection accepted from a [`Listener`]
+trait Stream: Read + Write + Send + Sized + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+    fn shutdown(&self, how: Shutdown) -> io::Result<()>;
+}
+
+impl Stream for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        TcpStream::shutdown(self, how)
+    }
+}
+
+#[cfg(unix)]
+impl Stream for UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        UnixStream::set_nonblocking(self, nonblocking)
+    }
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        UnixStream::shutdown(self, how)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(status: u16, body: &str) -> Self {
+        Response {
+            status,
+            headers: vec![("Content-Type", "text/plain; charset=utf-8".to_owned())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl Server {
+    /// Serve requests received on `listener` until `timeout` has elapsed or `stop` returns true.
+    /// Serves forever if neither is given.
+    pub fn run(
+        self,
+        listener: Listener,
+        timeout: Option<Duration>,
+        mut stop: Option<&mut dyn FnMut() -> bool>,
+    ) -> io::Result<()> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        if deadline.is_some() || stop.is_some() || self.live_reload.is_some() {
+            // Blocking on accept would prevent us from noticing when to stop or reload, so poll instead.
+            listener.set_nonblocking(true)?;
+        }
+
+        let server = Arc::new(self);
+        let mut last_poll = Instant::now();
+        let mut reloaded = latest_modification(&server.dir);
+        let mut previous = reloaded;
+        loop {
+            if let Some(live_reload) = &server.live_reload {
+                if last_poll.elapsed() >= LIVE_RELOAD_POLL {
+                    last_poll = Instant::now();
+                    let modified = latest_modification(&server.dir);
+                    // Wait for the files to stop changing so the page does not reload halfway through a build
+                    if modified == previous && modified != reloaded {
+                        live_reload.reload();
+                        reloaded = modified;
+                    }
+                    previous = modified;
+                }
+            }
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return Ok(());
+                }
+            }
+            if let Some(stop) = &mut stop {
+                if stop() {
+                    return Ok(());
+                }
+            }
+
+            let accepted = match &listener {
+                Listener::Tcp(listener) => listener
+                    .accept()
+                    .and_then(|(stream, _)| spawn_connection(&server, stream)),
+                #[cfg(unix)]
+                Listener::Unix(listener) => listener
+                    .accept()
+                    .and_then(|(stream, _)| spawn_connection(&server, stream)),
+            };
+            match accepted {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                // Failing to accept a single connection is not a reason to stop the server
+                Err(_) => {}
+            }
+        }
+    }
+
+    fn handle_connection<S: Stream>(&self, stream: S) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let request = match read_request(&mut reader)? {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+
+        if !self.host_allowed(&request) {
+            if self.access_log {
+                println!("{} {} 403", request.method, request.path);
+            }
+            return write_response(stream, &request, Response::text(403, "Forbidden"));
+        }
+
+        if let Some(live_reload) = &self.live_reload {
+            if request.path == LIVE_RELOAD_PATH {
+                return live_reload.accept(stream, &request);
+            }
+        }
+
+        let proxy = self
+            .proxies
+            .iter()
+            .find(|(prefix, _)| proxy_matches(prefix, &request.path));
+        if let Some((_, address)) = proxy {
+            if self.access_log {
+                println!("{} {} -> {address}", request.method, request.path);
+            }
+            return proxy_request(reader, stream, &request, address);
+        }
+
+        let response = self.respond(&request);
+        if self.access_log {
+            println!("{} {} {}", request.method, request.path, response.status);
+        }
+        write_response(stream, &request, response)
+    }
+
+    fn respond(&self, request: &Request) -> Response {
+        if request.method != "GET" && request.method != "HEAD" {
+            return Response::text(405, "Method Not Allowed");
+        }
+
+        let path = request.path.split(&['?', '#'][..]).next().unwrap_or("");
+        if path == HEALTH_PATH {
+            return Response {
+                status: 200,
+                headers: vec![
+                    ("Content-Type", "application/json".to_owned()),
+                    ("Cache-Control", "no-store".to_owned()),
+                ],
+                body: self.health.as_bytes().to_vec(),
+            };
+        }
+        let route = self.routes.iter().find(|(route, _)| route == path);
+        let below_base = match strip_base_path(&self.base_path, &request.path) {
+            BasePath::Below(rest) => Some(rest),
+            // The page links to its files relatively, which only resolves below the base path when it ends with `/`
+            BasePath::Redirect(location) if route.is_none() => {
+                return Response {
+                    status: 308,
+                    headers: vec![("Location", location)],
+                    body: vec![],
+                }
+            }
+            BasePath::Redirect(_) | BasePath::Outside => None,
+        };
+        let found = below_base.and_then(|rest| {
+            self.static_dirs
+                .iter()
+                .chain(std::iter::once(&self.dir))
+                .filter_map(|dir| resolve_path(dir, rest, &self.index_file))
+                .find(|file| file.is_file())
+        });
+        let file = match (route, found) {
+            (Some((_, file)), _) => file.clone(),
+            (None, Some(file)) => file,
+            // Single page apps handle routing client side, so any unknown path needs to load the app.
+            (None, None) if self.spa_fallback && below_base.is_some() => {
+                self.dir.join(&self.index_file)
+            }
+            (None, None) => return Response::text(404, "Not Found"),
+        };
+        match std::fs::read(&file) {
+            Ok(body) => Response {
+                status: 200,
+                headers: vec![
+                    ("Content-Type", self.content_type(&file)),
+                    ("Cache-Control", self.cache_control(&file).to_owned()),
+                ],
+                body,
+            },
+            Err(_) => Response::text(404, "Not Found"),
+        }
+    }
+
+    /// Whether the `Host` header of `request` is allowed by `allowed_hosts`
+    fn host_allowed(&self, request: &Request) -> bool {
+        if self.allowed_hosts.is_empty() {
+            return true;
+        }
+        let host = match request
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+        {
+            Some((_, host)) => host.as_str(),
+            None => return false,
+        };
+        // Remove the port, taking care not to split an ipv6 address such as `[::1]:8000`
+        let host = match host.strip_prefix('[') {
+            Some(host) => host.split(']').next().unwrap_or(host),
+            None => host.rsplit_once(':').map_or(host, |(host, _)| host),
+        };
+        host.eq_ignore_ascii_case("localhost")
+            || host.parse::<std::net::IpAddr>().is_ok()
+            || self
+                .allowed_hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+
+    fn content_type(&self, file: &Path) -> String {
+        let extension = file.extension().and_then(|x| x.to_str());
+        let custom = self
+            .mime_types
+            .iter()
+            .find(|(custom, _)| Some(custom.as_str()) == extension);
+        match custom {
+            Some((_, content_type)) => content_type.clone(),
+            None => content_type(file).to_owned(),
+        }
+    }
+
+    fn cache_control(&self, file: &Path) -> &'static str {
+        match self.cache_policy {
+            CachePolicy::NoCache => "no-store",
+            // A file can only be cached forever when a new build is guaranteed to change its name
+            CachePolicy::Immutable if is_content_hashed(file) => {
+                "public, max-age=31536000, immutable"
+            }
+            CachePolicy::Immutable => "no-cache",
+        }
+    }
+}
+
+fn spawn_connection<S: Stream>(server: &Arc<Server>, stream: S) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    let server = server.clone();
+    thread::spawn(move || {
+        // An error here just means the browser went away mid request, which is not worth reporting.
+        server.handle_connection(stream).ok();
+    });
+    Ok(())
+}
+
+/// Read the request line and headers of a http request, returns None if the request is malformed
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_owned(), path.to_owned()),
+        _ => return Ok(None),
+    };
+
+    let mut headers = vec![];
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+
+    Ok(Some(Request {
+        method,
+        path,
+        headers,
+    }))
+}
+
+/// Forward the request to the server at `address` and pipe data in both directions until either side closes the connection.
+/// Piping the raw connection means websocket upgrades and request bodies are forwarded without needing to understand them.
+fn proxy_request<S: Stream>(
+    mut reader: BufReader<S>,
+    mut client: S,
+    request: &Request,
+    address: &str,
+) -> io::Result<()> {
+    let mut backend = match TcpStream::connect(address) {
+        Ok(backend) => backend,
+        Err(err) => {
+            let response = Response::text(502, &format!("Failed to connect to {address}: {err}"));
+            return write_response(client, request, response);
+        }
+    };
+
+    let upgrade = request
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("upgrade"));
+    let mut head = format!("{} {} HTTP/1.1\r\n", request.method, request.path);
+    for (name, value) in &request.headers {
+        if name.eq_ignore_ascii_case("host") {
+            head.push_str(&format!("Host: {address}\r\n"));
+        } else if name.eq_ignore_ascii_case("connection") && !upgrade {
+            // Following requests on this connection might not be for the proxy, so dont let it be reused.
+            head.push_str("Connection: close\r\n");
+        } else {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+    }
+    head.push_str("\r\n");
+    backend.write_all(head.as_bytes())?;
+
+    // The reader may have already buffered part of the request body, so copy from it rather than the raw stream.
+    let mut backend_writer = backend.try_clone()?;
+    thread::spawn(move || {
+        io::copy(&mut reader, &mut backend_writer).ok();
+        backend_writer.shutdown(Shutdown::Write).ok();
+    });
+    io::copy(&mut backend, &mut client)?;
+    client.shutdown(Shutdown::Both).ok();
+    Ok(())
+}
+
+fn write_response(mut stream: impl Write, request: &Request, response: Response) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        reason_phrase(response.status)
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    ));
+
+    stream.write_all(head.as_bytes())?;
+    if request.method != "HEAD" {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()
+}
+
+/// The most recent modification time of the files within `dir`
+fn latest_modification(dir: &Path) -> Option<SystemTime> {
+    let mut latest = None;
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let modified = match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => latest_modification(&entry.path()),
+            _ => entry.metadata().and_then(|x| x.modified()).ok(),
+        };
+        latest = latest.max(modified);
+    }
+    latest
+}
+
+/// Whether the file name contains a hash of its contents, like the `app-3f2a9c1d.js` or `app.3f2a9c1d.wasm` produced by bundlers.
+/// Recognized as a segment of at least 8 hex digits, including a decimal digit to avoid matching words such as `deadbeef`.
+fn is_content_hashed(file: &Path) -> bool {
+    let stem = match file.file_stem().and_then(|x| x.to_str()) {
+        Some(stem) => stem,
+        None => return false,
+    };
+    stem.split(&['-', '.', '_'][..]).any(|segment| {
+        segment.len() >= 8
+            && segment.chars().all(|x| x.is_ascii_hexdigit())
+            && segment.chars().any(|x| x.is_ascii_digit())
+    })
+}
+
+/// Map the path of a request onto a file within `dir`.
+/// Returns None if the path is invalid or would escape `dir`.
+/// Returns true if the request for `path` should be forwarded by the proxy for `prefix`.
+/// The prefix must match whole path segments, so a proxy for `/api` does not capture `/apiary`.
+fn proxy_matches(prefix: &str, path: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => prefix.ends_with('/') || rest.is_empty() || rest.starts_with(&['/', '?'][..]),
+        None => false,
+    }
+}
+
+enum BasePath<'a> {
+    /// The request is below the base path, with the remaining path starting with `/`
+    Below(&'a str),
+    /// The request is for the base path without its trailing `/`, and should be redirected to the given location
+    Redirect(String),
+    Outside,
+}
+
+/// Locate the request for `path` relative to `base_path`, which starts and ends with `/`
+fn strip_base_path<'a>(base_path: &str, path: &'a str) -> BasePath<'a> {
+    let base = base_path.trim_end_matches('/');
+    match path.strip_prefix(base) {
+        Some(rest) if rest.starts_with('/') => BasePath::Below(rest),
+        Some(rest) if rest.is_empty() || rest.starts_with('?') => {
+            BasePath::Redirect(format!("{base}/{rest}"))
+        }
+        _ => BasePath::Outside,
+    }
+}
+
+fn resolve_path(dir: &Path, request_path: &str, index_file: &str) -> Option<PathBuf> {
+    let path = request_path.split(&['?', '#'][..]).next()?;
+    let path = percent_decode(path)?;
+
+    let mut file = dir.to_owned();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            // A segment such as `C:` or `a\..\..` would replace or escape `dir` on windows rather than extending it,
+            // so only accept segments that are a single plain file name on every platform.
+            segment => {
+                let mut components = Path::new(segment).components();
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(_)), None) if !segment.contains(&['\\', ':'][..]) => {
+                        file.push(segment)
+                    }
+                    _ => return None,
+                }
+            }
+        }
+    }
+    if file.is_dir() {
+        file.push(index_file);
+    }
+    Some(file)
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            result.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(result).ok()
+}
+
+fn content_type(file: &Path) -> &'static str {
+    match file.extension().and_then(|x| x.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        // Must be application/wasm or `WebAssembly.instantiateStreaming` will refuse to load it
+        Some("wasm") => "application/wasm",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") | Some("map") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        502 => "Bad Gateway",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decode_valid() {
+        assert_eq!(percent_decode("a%20b").as_deref(), Some("a b"));
+        assert_eq!(percent_decode("%2e%2e").as_deref(), Some(".."));
+        assert_eq!(percent_decode("%5c").as_deref(), Some("\\"));
+    }
+
+    #[test]
+    fn percent_decode_invalid() {
+        assert_eq!(percent_decode("%"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%ff"), None);
+    }
+
+    #[test]
+    fn resolve_path_within_dir() {
+        let dir = Path::new("served");
+        assert_eq!(
+            resolve_path(dir, "/pkg/app.js?v=1", "index.html"),
+            Some(dir.join("pkg").join("app.js"))
+        );
+        assert_eq!(
+            resolve_path(dir, "/./a%20b.txt", "index.html"),
+            Some(dir.join("a b.txt"))
+        );
+    }
+
+    #[test]
+    fn resolve_path_rejects_escapes() {
+        let dir = Path::new("served");
+        for path in [
+            "/../secret",
+            "/a/../../secret",
+            "/%2e%2e/secret",
+            "/%2E%2E/secret",
+            "/a%5c..%5c..%5csecret",
+            "/C:/Windows/win.ini",
+            "/C%3a/Windows/win.ini",
+        ] {
+            assert_eq!(resolve_path(dir, path, "index.html"), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn content_hashed_file_names() {
+        for file in [
+            "app-3f2a9c1d.js",
+            "app.3f2a9c1d.wasm",
+            "app_bg-0123456789abcdef.wasm",
+            "assets/3f2a9c1d.css",
+        ] {
+            assert!(is_content_hashed(Path::new(file)), "{}", file);
+        }
+        for file in [
+            "app.js",
+            "app_bg.wasm",
+            "index.html",
+            "deadbeef.js",
+            "app-3f2a9c1.js",
+        ] {
+            assert!(!is_content_hashed(Path::new(file)), "{}", file);
+        }
+    }
+
+    #[test]
+    fn base_path() {
+        assert!(matches!(strip_base_path("/", "/"), BasePath::Below("/")));
+        assert!(matches!(
+            strip_base_path("/", "/app.js?v=1"),
+            BasePath::Below("/app.js?v=1")
+        ));
+        assert!(matches!(
+            strip_base_path("/app/", "/app/app.js"),
+            BasePath::Below("/app.js")
+        ));
+        assert!(matches!(
+            strip_base_path("/app/", "/app/"),
+            BasePath::Below("/")
+        ));
+        assert!(
+            matches!(strip_base_path("/app/", "/app"), BasePath::Redirect(location) if location == "/app/")
+        );
+        assert!(
+            matches!(strip_base_path("/app/", "/app?x=1"), BasePath::Redirect(location) if location == "/app/?x=1")
+        );
+        assert!(matches!(strip_base_path("/app/", "/"), BasePath::Outside));
+        assert!(matches!(
+            strip_base_path("/app/", "/apple"),
+            BasePath::Outside
+        ));
+    }
+
+    #[test]
+    fn proxy_prefix_matches_whole_segments() {
+        assert!(proxy_matches("/api", "/api"));
+        assert!(proxy_matches("/api", "/api/users"));
+        assert!(proxy_matches("/api", "/api?page=2"));
+        assert!(proxy_matches("/api/", "/api/users"));
+        assert!(!proxy_matches("/api", "/apiary"));
+        assert!(!proxy_matches("/api", "/api-docs"));
+        assert!(!proxy_matches("/api/", "/api"));
+        assert!(!proxy_matches("/api", "/"));
+    }
+}
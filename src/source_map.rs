@@ -0,0 +1,91 @@
+//! Generate line level source maps for the js glue
+use crate::minify::Position;
+
+/// A source map for the generated `file` whose lines start at `lines` within the original `source`.
+/// The original is embedded in the map as `source_name` so that no extra file needs to be served.
+pub fn generate(file: &str, source_name: &str, source: &str, lines: &[Option<Position>]) -> String {
+    let mut mappings = String::new();
+    // Everything except the generated column is relative to the previous segment in the whole map
+    let mut previous = (0, 0);
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            mappings.push(';');
+        }
+        if let Some((line, column)) = *line {
+            // generated column, source index, source line, source column
+            for value in [
+                0,
+                0,
+                line as i64 - previous.0 as i64,
+                column as i64 - previous.1 as i64,
+            ] {
+                encode_vlq(&mut mappings, value);
+            }
+            previous = (line, column);
+        }
+    }
+    serde_json::json!({
+        "version": 3,
+        "file": file,
+        "sources": [source_name],
+        "sourcesContent": [source],
+        "names": [],
+        "mappings": mappings,
+    })
+    .to_string()
+}
+
+/// Append `value` as a base64 VLQ, the sign is stored in the lowest bit
+fn encode_vlq(out: &mut String, value: i64) {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vlq(value: i64) -> String {
+        let mut out = String::new();
+        encode_vlq(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn vlq_values() {
+        assert_eq!(vlq(0), "A");
+        assert_eq!(vlq(1), "C");
+        assert_eq!(vlq(-1), "D");
+        assert_eq!(vlq(15), "e");
+        assert_eq!(vlq(16), "gB");
+        assert_eq!(vlq(-16), "hB");
+    }
+
+    #[test]
+    fn mappings_are_relative() {
+        let map = generate(
+            "a.js",
+            "a.unminified.js",
+            "",
+            &[Some((0, 0)), None, Some((2, 4))],
+        );
+        let map: serde_json::Value = serde_json::from_str(&map).unwrap();
+        assert_eq!(map["mappings"], "AAAA;;AAEI");
+        assert_eq!(map["sources"][0], "a.unminified.js");
+    }
+}
@@ -1,60 +1,106 @@
 //! Get the target directory for cargo-run-wasm
+use crate::metadata::CargoMetadata;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::thread;
+use std::time::Duration;
 
+/// How many times `cargo metadata` is run before giving up
+const METADATA_ATTEMPTS: u32 = 3;
+
+#[derive(Clone)]
 pub struct CargoDirectories {
     pub workspace_root: PathBuf,
     pub target_directory: PathBuf,
 }
 
 impl CargoDirectories {
-    fn from_cargo(cargo_executable: &str, manifest_dir: &Path) -> Self {
-        let output = Command::new(cargo_executable)
-            .current_dir(manifest_dir)
-            .args(["metadata", "--no-deps", "--format-version=1"])
-            .output()
-            .unwrap();
-
-        let result = String::from_utf8(output.stdout).expect("Command output should be utf-8");
-        let value = serde_json::from_str::<serde_json::Value>(&result)
-            .expect("Should match the CargoMetadata definition");
-        let obj = value.as_object().expect("Metadata should be object");
-        let target_directory = PathBuf::from(
-            obj.get("target_directory")
-                .expect("Should have target directory")
-                .as_str()
-                .unwrap()
-                .to_owned(),
-        );
-        let workspace_root = PathBuf::from(
-            obj.get("workspace_root")
-                .expect("Should have target directory")
-                .as_str()
-                .unwrap()
-                .to_owned(),
-        );
-        CargoDirectories {
-            target_directory,
-            workspace_root,
-        }
+    fn from_cargo(
+        cargo_executable: &Path,
+        manifest_dir: &Path,
+        cargo_flags: &[&str],
+        toolchain: Option<&str>,
+    ) -> Result<Self, String> {
+        // `cargo metadata` can fail transiently in CI, e.g. when cargo can not be spawned or prints something other than the metadata, so retry with backoff.
+        // An error reported by cargo itself, such as an invalid manifest, will not go away so it is returned immediately.
+        let mut attempt = 1;
+        let metadata = loop {
+            match CargoMetadata::fetch(cargo_executable, manifest_dir, cargo_flags, toolchain) {
+                Ok(metadata) => break metadata,
+                Err(err) if !err.transient => {
+                    return Err(format!(
+                        "Could not locate the target directory: {}",
+                        err.message
+                    ))
+                }
+                Err(err) if attempt == METADATA_ATTEMPTS => {
+                    return Err(format!(
+                        "Could not locate the target directory after {attempt} attempts: {}",
+                        err.message
+                    ))
+                }
+                Err(_) => {
+                    thread::sleep(Duration::from_millis(100 * 2u64.pow(attempt - 1)));
+                    attempt += 1;
+                }
+            }
+        };
+        Ok(CargoDirectories {
+            target_directory: metadata.target_directory,
+            workspace_root: metadata.workspace_root,
+        })
     }
 
-    pub fn new(cargo_executable: &str) -> CargoDirectories {
-        let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    /// The directories of the workspace containing `package_dir`, or if None the workspace run-wasm was launched from.
+    /// `cargo_flags` such as `--offline` and the rustup `toolchain` are passed on to `cargo metadata` if it needs to be asked for the directories.
+    pub fn new(
+        cargo_executable: &Path,
+        package_dir: Option<&Path>,
+        cargo_flags: &[&str],
+        toolchain: Option<&str>,
+    ) -> Result<CargoDirectories, String> {
+        let current_dir = || {
+            std::env::current_dir()
+                .map_err(|err| format!("Could not access the current directory: {err}"))
+        };
+        let manifest_dir = match (package_dir, std::env::var_os("CARGO_MANIFEST_DIR")) {
+            // The parents of a relative path are only found once it is made absolute
+            (Some(package_dir), _) => current_dir()?.join(package_dir),
+            (None, Some(manifest_dir)) => PathBuf::from(manifest_dir),
+            // The runner binary was launched directly instead of via `cargo run`, so search from the current directory instead.
+            (None, None) => current_dir()?,
+        };
+
+        // A target directory moved by the env var would not be found by the search below, or worse a stale `target` directory would be.
+        // Finding the workspace root ourselves avoids running `cargo metadata`, which may not be possible with `--offline` or `--frozen`.
+        let target_dir_env = std::env::var_os("CARGO_TARGET_DIR")
+            .or_else(|| std::env::var_os("CARGO_BUILD_TARGET_DIR"));
+        if let Some(target_dir) = target_dir_env {
+            if let Some(workspace_root) = find_workspace_root(&manifest_dir) {
+                // cargo resolves a relative target directory against the directory it was run from
+                let target_directory = current_dir()?.join(target_dir);
+                return Ok(CargoDirectories {
+                    target_directory,
+                    workspace_root,
+                });
+            }
+        }
 
         // First try to find the directories ourselves.
         // We can rely on Cargo.toml being correct as Cargo issues warnings when unused/incorrect Cargo.toml's are left around.
-        // It is however possible for this to return false positives if the user leaves an unused directory named target next to their Cargo.toml
+        // It is however possible for this to return false positives if the user leaves an unused directory named target next to their Cargo.toml.
         // I think this is acceptable though.
+        // A file named target is not mistaken for a target directory, instead we fall back to asking cargo.
         let mut workspace_root = manifest_dir.clone();
+        let mut found_cargo_toml = false;
         loop {
             let target = workspace_root.join("target");
             let cargo_toml = workspace_root.join("Cargo.toml");
-            if target.exists() && cargo_toml.exists() {
-                return CargoDirectories {
+            found_cargo_toml |= cargo_toml.is_file();
+            if target.is_dir() && cargo_toml.is_file() {
+                return Ok(CargoDirectories {
                     target_directory: target,
                     workspace_root,
-                };
+                });
             }
             if !workspace_root.pop() {
                 break;
@@ -66,6 +112,30 @@ impl CargoDirectories {
         // This is because:
         // 1. In order for cargo-run-wasm to be running a target directory must have been created for the cargo-run-wasm binary to live in.
         // 2. If the target directory is in its default location it can always be found by traversing parent directories because the workspace can only create its child packages in a child directory
-        CargoDirectories::from_cargo(cargo_executable, &manifest_dir)
+        if !found_cargo_toml {
+            return Err(format!(
+                "Could not locate Cargo.toml in {manifest_dir:?} or any of its parents; run cargo-run-wasm from within a cargo package"
+            ));
+        }
+        CargoDirectories::from_cargo(cargo_executable, &manifest_dir, cargo_flags, toolchain)
+    }
+}
+
+/// The nearest parent of `dir` with a Cargo.toml declaring a `[workspace]`, otherwise the nearest parent with a Cargo.toml.
+/// Returns None if a Cargo.toml can not be read, leaving cargo to decide.
+fn find_workspace_root(dir: &Path) -> Option<PathBuf> {
+    let mut package_root = None;
+    for dir in dir.ancestors() {
+        let cargo_toml = dir.join("Cargo.toml");
+        if !cargo_toml.is_file() {
+            continue;
+        }
+        let manifest: toml::value::Table =
+            toml::from_str(&std::fs::read_to_string(&cargo_toml).ok()?).ok()?;
+        if manifest.contains_key("workspace") {
+            return Some(dir.to_owned());
+        }
+        package_root.get_or_insert_with(|| dir.to_owned());
     }
+    package_root
 }
@@ -0,0 +1,186 @@
+//! Processing of the index.html template
+
+/// Replace every `{{key}}` placeholder in `template` with the matching value.
+///
+/// This is done in a single pass so a substituted value is never itself treated as a placeholder.
+/// Placeholders without a matching value are left untouched.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let replacement = after.find("}}").and_then(|end| {
+            let key = &after[..end];
+            values
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| (end, value))
+        });
+        match replacement {
+            Some((end, value)) => {
+                result.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                result.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The values substituted into index.template.html, each one already escaped for where it is placed
+pub struct Index<'a> {
+    pub name: &'a str,
+    pub lang: &'a str,
+    pub dir: &'a str,
+    pub script_src: &'a str,
+    pub init_args: &'a str,
+    pub css: &'a str,
+    pub body: &'a str,
+    pub meta: &'a str,
+    pub head: &'a str,
+    pub init: &'a str,
+}
+
+impl Index<'_> {
+    pub fn render(&self) -> String {
+        render(
+            include_str!("index.template.html"),
+            &[
+                ("name", self.name),
+                ("lang", self.lang),
+                ("dir", self.dir),
+                ("script_src", self.script_src),
+                ("init_args", self.init_args),
+                ("css", self.css),
+                ("body", self.body),
+                ("meta", self.meta),
+                ("head", self.head),
+                ("init", self.init),
+            ],
+        )
+    }
+}
+
+/// The page linking to each of the targets when multiple are built
+pub fn render_listing(links: &str) -> String {
+    render(include_str!("listing.template.html"), &[("links", links)])
+}
+
+/// Escape a value so it can be placed within a double quoted html attribute
+pub fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Quote a value as a javascript string literal that can be placed within a `<script>` element
+pub fn escape_js_string(value: &str) -> String {
+    // `<` is escaped so that the value can never close the script element
+    serde_json::to_string(value)
+        .unwrap()
+        .replace('<', "\\u003c")
+}
+
+/// Encode `bytes` as standard base64 with padding
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::with_capacity(bytes.len() / 3 * 4 + 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// Reject css that could escape the `<style>` element it is placed in.
+/// All whitespace is ignored and the comparison is case insensitive, to catch attempts to slip past a naive check.
+pub fn validate_css_strict(css: &str) -> Result<(), String> {
+    let normalized: String = css
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    for disallowed in ["</style", "<script"] {
+        if normalized.contains(disallowed) {
+            return Err(format!(
+                "`{disallowed}` detected in the css. This is disallowed to prevent injecting elements into the DOM."
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that `rendered` contains every one of `values` and has no placeholders left
+    fn assert_rendered_fully(rendered: &str, values: &[&str]) {
+        for value in values {
+            assert!(
+                rendered.contains(value),
+                "The template has no placeholder for {}",
+                value
+            );
+        }
+        assert!(
+            !rendered.contains("{{"),
+            "The template has a placeholder without a value:\n{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn index_template_placeholders() {
+        let index = Index {
+            name: "NAME_VALUE",
+            lang: "LANG_VALUE",
+            dir: "DIR_VALUE",
+            script_src: "SCRIPT_SRC_VALUE",
+            init_args: "INIT_ARGS_VALUE",
+            css: "CSS_VALUE",
+            body: "BODY_VALUE",
+            meta: "META_VALUE",
+            head: "HEAD_VALUE",
+            init: "INIT_VALUE",
+        };
+        assert_rendered_fully(
+            &index.render(),
+            &[
+                index.name,
+                index.lang,
+                index.dir,
+                index.script_src,
+                index.init_args,
+                index.css,
+                index.body,
+                index.meta,
+                index.head,
+                index.init,
+            ],
+        );
+    }
+
+    #[test]
+    fn listing_template_placeholders() {
+        assert_rendered_fully(&render_listing("LINKS_VALUE"), &["LINKS_VALUE"]);
+    }
+}
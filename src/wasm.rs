@@ -0,0 +1,159 @@
+//! Just enough of a wasm parser to list the imports of a module
+
+/// The module and field name of every import in the wasm module `bytes`
+pub fn imports(bytes: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let mut reader = Reader { bytes, position: 0 };
+    if &reader.take(8)?[..4] != b"\0asm" {
+        return Err("not a wasm module".to_owned());
+    }
+
+    while reader.position < bytes.len() {
+        let id = reader.byte()?;
+        let size = reader.leb128()? as usize;
+        let section = reader.take(size)?;
+        // A module has at most one import section
+        const IMPORT_SECTION: u8 = 2;
+        if id == IMPORT_SECTION {
+            return parse_imports(section);
+        }
+    }
+    Ok(vec![])
+}
+
+fn parse_imports(section: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let mut reader = Reader {
+        bytes: section,
+        position: 0,
+    };
+    let count = reader.leb128()?;
+    let mut imports = vec![];
+    for _ in 0..count {
+        let module = reader.name()?;
+        let field = reader.name()?;
+        // Skip the description of the import to reach the next one
+        match reader.byte()? {
+            // function: type index
+            0x00 => {
+                reader.leb128()?;
+            }
+            // table: element type and limits
+            0x01 => {
+                reader.byte()?;
+                reader.limits()?;
+            }
+            // memory: limits
+            0x02 => reader.limits()?,
+            // global: value type and mutability
+            0x03 => {
+                reader.take(2)?;
+            }
+            // tag: attribute and type index
+            0x04 => {
+                reader.byte()?;
+                reader.leb128()?;
+            }
+            kind => return Err(format!("unknown import kind {kind}")),
+        }
+        imports.push((module, field));
+    }
+    Ok(imports)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.position.saturating_add(len);
+        let bytes = self
+            .bytes
+            .get(self.position..end)
+            .ok_or_else(|| "unexpected end of the wasm module".to_owned())?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn leb128(&mut self) -> Result<u64, String> {
+        let mut result = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            result |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err("invalid integer in the wasm module".to_owned())
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let len = self.leb128()? as usize;
+        let bytes = self.take(len)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn limits(&mut self) -> Result<(), String> {
+        let flags = self.byte()?;
+        self.leb128()?;
+        if flags & 1 != 0 {
+            self.leb128()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(sections: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        for (id, contents) in sections {
+            bytes.push(*id);
+            bytes.push(contents.len() as u8);
+            bytes.extend_from_slice(contents);
+        }
+        bytes
+    }
+
+    #[test]
+    fn function_and_memory_imports() {
+        let types: &[u8] = &[1, 0x60, 0, 0];
+        let section: Vec<u8> = [
+            // count
+            &[2][..],
+            // env.f, a function with type 0
+            &b"\x03env\x01f\x00\x00"[..],
+            // env.mem, a memory with a min and max
+            &b"\x03env\x03mem\x02\x01\x01\x02"[..],
+        ]
+        .concat();
+        assert_eq!(
+            imports(&module(&[(1, types), (2, &section[..])])).unwrap(),
+            [
+                ("env".to_owned(), "f".to_owned()),
+                ("env".to_owned(), "mem".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn no_imports() {
+        assert!(imports(&module(&[(1, &[1, 0x60, 0, 0][..])]))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn invalid_modules() {
+        assert!(imports(b"not wasm").is_err());
+        assert!(imports(b"\0asm").is_err());
+        // The section claims to be longer than the module
+        assert!(imports(b"\0asm\x01\0\0\0\x02\x10\x01").is_err());
+    }
+}